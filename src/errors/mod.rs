@@ -1,6 +1,50 @@
-#[derive(Clone, Debug)]
+use std::fmt;
+use std::sync::Arc;
+use thiserror::Error;
+
+/// Where in the RTF input a problem was found
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Location {
+    /// byte offset from the start of the input
+    pub offset: usize,
+    /// group nesting depth (`{` not yet closed)
+    pub depth: usize,
+    /// last control word seen before the problem, without the backslash
+    pub control_word: Option<String>,
+}
+
+impl fmt::Display for Location {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "byte {} (group depth {}", self.offset, self.depth)?;
+        if let Some(word) = &self.control_word {
+            write!(f, ", after \\{}", word)?;
+        }
+        write!(f, ")")
+    }
+}
+
+#[derive(Clone, Debug, Error)]
 pub enum Errors {
-    Base64DecodeError(base64::DecodeError),
-    DocxBuildError,
-    ParseError,
+    #[error("invalid base64 input: {0}")]
+    Base64DecodeError(#[from] base64::DecodeError),
+    #[error("failed to build docx: {0}")]
+    DocxBuildError(#[source] Arc<docx_rs::DocxError>),
+    #[error("parse error at {location}: {message}")]
+    ParseError { location: Location, message: String },
+    #[error("invalid document: {0}")]
+    ValidationError(crate::Warning),
+    #[error("failed to read input: {0}")]
+    IoError(#[source] Arc<std::io::Error>),
+}
+
+impl From<docx_rs::DocxError> for Errors {
+    fn from(error: docx_rs::DocxError) -> Self {
+        Errors::DocxBuildError(Arc::new(error))
+    }
+}
+
+impl From<std::io::Error> for Errors {
+    fn from(error: std::io::Error) -> Self {
+        Errors::IoError(Arc::new(error))
+    }
 }
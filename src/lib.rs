@@ -8,4 +8,3 @@ extern crate log;
 extern crate lazy_static;
 
 pub use crate::rtf::*;
-pub use errors::*;
@@ -1,23 +1,17 @@
-#[derive(Clone, Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq, Default)]
 pub struct Color {
     pub b: u8,
     pub g: u8,
     pub r: u8,
 }
 
-impl Default for Color {
-    fn default() -> Color {
-        Color { b: 0, g: 0, r: 0 }
+impl From<Color> for String {
+    fn from(val: Color) -> Self {
+        format!("{:02x}{:02x}{:02x}", val.r, val.g, val.b)
     }
 }
-
-impl Into<String> for Color {
-    fn into(self) -> String {
-        format!("{:02x}{:02x}{:02x}", self.r, self.g, self.b)
-    }
-}
-impl Into<String> for &Color {
-    fn into(self) -> String {
-        format!("{:02x}{:02x}{:02x}", self.r, self.g, self.b)
+impl From<&Color> for String {
+    fn from(val: &Color) -> Self {
+        format!("{:02x}{:02x}{:02x}", val.r, val.g, val.b)
     }
 }
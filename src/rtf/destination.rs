@@ -3,20 +3,16 @@ use super::*;
 pub enum Destination {
     Text(Text),
     Bytes(Vec<u8>),
+    /// Byte destination whose content is only counted, see
+    /// [`ParseOptions::keep_binary_destinations`]
+    Skipped(usize),
 }
 impl Destination {
     pub fn as_bytes(&self) -> Vec<u8> {
         match self {
-            Destination::Text(text) => text
-                .pages
-                .iter()
-                .flat_map(|page| page.sections.iter())
-                .flat_map(|section| section.paras.iter())
-                .flat_map(|paras| paras.lines.iter())
-                .flat_map(|line| line.bytes.iter())
-                .cloned()
-                .collect(),
+            Destination::Text(text) => text.to_string().into_bytes(),
             Destination::Bytes(bytes) => bytes.clone(),
+            Destination::Skipped(_) => vec![],
         }
     }
     pub fn new_page(&mut self) {
@@ -39,41 +35,47 @@ impl Destination {
             text.new_line()
         }
     }
-    #[allow(clippy::too_many_arguments)]
+    /// Appends text to a text destination; false when this is a byte destination
+    /// and the text was dropped
     pub fn append_text(
         &mut self,
-        new_bytes: &[u8],
-        font: Option<i32>,
-        style: Option<FontStyle>,
+        new_text: &str,
+        props: &CharacterProperties,
         para_style: Option<ParagraphStyle>,
         stylesheet: Option<i32>,
         in_table: bool,
-        encoding: &'static encoding_rs::Encoding,
-    ) {
-        if new_bytes.is_empty() {
-            return;
-        }
-        if let Destination::Text(text) = self {
-            text.last_or_new_paragraph(stylesheet, para_style, in_table);
+    ) -> bool {
+        match self {
+            Destination::Text(text) => {
+                if !new_text.is_empty() {
+                    text.last_or_new_paragraph(stylesheet, para_style, in_table);
 
-            {
-                let base_encoding = text.encoding;
-                let line = text.last_or_new_line(font, style, Some(encoding));
-                line.bytes.extend(new_bytes);
-                if base_encoding != Some(encoding) {
-                    line.encoding = Some(encoding);
+                    let line = text.last_or_new_line(props);
+                    line.text.push_str(new_text);
                 }
+                true
+            }
+            Destination::Bytes(_) => new_text.is_empty(),
+            Destination::Skipped(len) => {
+                *len += new_text.len();
+                true
             }
-        } else {
-            panic!("Programmer error: attempting to add text to a byte destination");
         }
     }
 
-    pub fn append_bytes(&mut self, new_bytes: &[u8]) {
-        if let Destination::Bytes(bytes) = self {
-            bytes.extend(new_bytes);
-        } else {
-            panic!("Programmer error: attempting to add bytes to a text destination");
+    /// Appends bytes to a byte destination; false when this is a text destination
+    /// and the bytes were dropped
+    pub fn append_bytes(&mut self, new_bytes: &[u8]) -> bool {
+        match self {
+            Destination::Bytes(bytes) => {
+                bytes.extend(new_bytes);
+                true
+            }
+            Destination::Skipped(len) => {
+                *len += new_bytes.len();
+                true
+            }
+            Destination::Text(_) => new_bytes.is_empty(),
         }
     }
 }
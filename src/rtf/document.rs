@@ -1,103 +1,391 @@
 use super::*;
 
+/// Fully processed RTF document.
+///
+/// Produced once by [`Rtf::parse`], then borrowed by every renderer
+/// (plain text, DOCX, ...), so converting to several formats doesn't
+/// re-process the token stream.
+#[derive(Clone, Debug)]
+pub struct Document {
+    /// Body text (`\rtf1` destination)
+    pub body: Option<Text>,
+    pub fonts: HashMap<i32, Font>,
+    pub stylesheets: HashMap<i32, StyleSheet>,
+    pub colors: Vec<Color>,
+    pub default_font_number: Option<i32>,
+    pub defaults: DocumentDefaults,
+    pub info: DocumentInfo,
+    /// Every other destination found in the document (headers, footers, footnotes, ...)
+    pub destinations: HashMap<String, Destination>,
+    /// Malformed or unsupported constructs found while parsing
+    pub diagnostics: Diagnostics,
+}
+impl Document {
+    /// Entry `index` of the color table (`\cfN`, `\ulcN`, ...), `None` for 0 (automatic)
+    pub fn color(&self, index: usize) -> Option<&Color> {
+        index
+            .checked_sub(1)
+            .and_then(|index| self.colors.get(index))
+    }
+    /// The paragraph style of `para`, `\s0` when it names none
+    fn paragraph_stylesheet(&self, para: &Paragraph) -> Option<&StyleSheet> {
+        self.stylesheets
+            .get(&para.stylesheet.unwrap_or(0))
+            .filter(|stylesheet| stylesheet.kind == StyleKind::Paragraph)
+    }
+    /// Paragraph formatting of `para`: its style chain, then its direct formatting
+    pub fn paragraph_format(&self, para: &Paragraph) -> ParagraphStyle {
+        let defaults = self.defaults.para_style.clone().unwrap_or_default();
+        let format = match self
+            .paragraph_stylesheet(para)
+            .and_then(|stylesheet| stylesheet.resolved_para_style(&self.stylesheets))
+        {
+            Some(style) => style.inherit(&defaults),
+            None => defaults,
+        };
+        match para.style.as_ref() {
+            Some(direct) => direct.inherit(&format),
+            None => format,
+        }
+    }
+    /// Character formatting of `line` in `para`: the document defaults, the paragraph style chain,
+    /// the `\cs` character style chain, then direct formatting.
+    ///
+    /// Toggles turned off explicitly, like `\b0` or `\ulnone`, override the styles.
+    pub fn run_format(&self, para: &Paragraph, line: &Line) -> RunFormat {
+        let char_stylesheet = line
+            .char_style
+            .and_then(|number| self.stylesheets.get(&number))
+            .filter(|stylesheet| stylesheet.kind == StyleKind::Character);
+        let mut format = RunFormat {
+            font: self.default_font_number,
+            style: self.defaults.font_style.clone().unwrap_or_default(),
+        };
+        for stylesheet in [self.paragraph_stylesheet(para), char_stylesheet]
+            .iter()
+            .flatten()
+        {
+            if let Some(style) = stylesheet.resolved_font_style(&self.stylesheets) {
+                format.style = style.inherit(&format.style);
+            }
+            format.font = stylesheet.resolved_font(&self.stylesheets).or(format.font);
+        }
+        if let Some(direct) = line.style.as_ref() {
+            format.style = direct.inherit(&format.style);
+        }
+        format.font = line.font.or(format.font);
+        format
+    }
+    pub fn to_text(&self) -> String {
+        self.body
+            .as_ref()
+            .map(|text| text.to_string())
+            .unwrap_or_default()
+    }
+}
+
+/// Metadata from the `\info` group
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct DocumentInfo {
+    pub title: Option<String>,
+    pub subject: Option<String>,
+    pub author: Option<String>,
+    pub manager: Option<String>,
+    pub company: Option<String>,
+    pub operator: Option<String>,
+    pub category: Option<String>,
+    pub keywords: Option<String>,
+    pub comment: Option<String>,
+    pub doccomm: Option<String>,
+    pub hlinkbase: Option<String>,
+    pub created: Option<DocumentTime>,
+    pub revised: Option<DocumentTime>,
+    pub printed: Option<DocumentTime>,
+    pub backed_up: Option<DocumentTime>,
+    pub version: Option<i32>,
+    pub editing_minutes: Option<i32>,
+    pub pages: Option<i32>,
+    pub words: Option<i32>,
+    pub characters: Option<i32>,
+}
+impl DocumentInfo {
+    pub(crate) const TEXT_DESTINATIONS: [&'static str; 11] = [
+        "title",
+        "subject",
+        "author",
+        "manager",
+        "company",
+        "operator",
+        "category",
+        "keywords",
+        "comment",
+        "doccomm",
+        "hlinkbase",
+    ];
+    fn set_text(&mut self, name: &str, value: String) {
+        let field = match name {
+            "title" => &mut self.title,
+            "subject" => &mut self.subject,
+            "author" => &mut self.author,
+            "manager" => &mut self.manager,
+            "company" => &mut self.company,
+            "operator" => &mut self.operator,
+            "category" => &mut self.category,
+            "keywords" => &mut self.keywords,
+            "comment" => &mut self.comment,
+            "doccomm" => &mut self.doccomm,
+            "hlinkbase" => &mut self.hlinkbase,
+            _ => return,
+        };
+        *field = Some(value);
+    }
+}
+
+/// Document-wide defaults from the header
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct DocumentDefaults {
+    /// `\deffN`
+    pub font: Option<i32>,
+    /// `\stshfloch`, `\stshfhich`, `\stshfdbch` and `\stshfbi` (or `\adeff`),
+    /// the fonts the stylesheet uses for each kind of character
+    pub style_fonts: AssociatedFonts,
+    /// `\loch\afN` and so on inside `\defchp`
+    pub associated_fonts: AssociatedFonts,
+    /// `\defchp`
+    pub font_style: Option<FontStyle>,
+    /// `\defpap`
+    pub para_style: Option<ParagraphStyle>,
+    /// `\deflangN`
+    pub language: Option<i32>,
+    /// `\deflangfeN`
+    pub east_asian_language: Option<i32>,
+    /// `\adeflangN`
+    pub complex_language: Option<i32>,
+    /// `\deftabN`, the distance between default tab stops in twips
+    pub tab_width: Option<i32>,
+}
+impl DocumentDefaults {
+    /// The default font for each kind of character: `\defchp`, then `\stshf*`, then `\deff`
+    pub fn fonts(&self) -> AssociatedFonts {
+        let pick =
+            |defchp: Option<i32>, stylesheet: Option<i32>| defchp.or(stylesheet).or(self.font);
+        AssociatedFonts {
+            ascii: pick(self.associated_fonts.ascii, self.style_fonts.ascii),
+            high_ansi: pick(self.associated_fonts.high_ansi, self.style_fonts.high_ansi),
+            east_asia: pick(self.associated_fonts.east_asia, self.style_fonts.east_asia),
+            complex: pick(self.associated_fonts.complex, self.style_fonts.complex),
+        }
+    }
+}
+
+/// Date and time from `\creatim`, `\revtim`, `\printim` and `\buptim`
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct DocumentTime {
+    pub year: Option<i32>,
+    pub month: Option<i32>,
+    pub day: Option<i32>,
+    pub hour: Option<i32>,
+    pub minute: Option<i32>,
+    pub second: Option<i32>,
+}
+impl DocumentTime {
+    fn from_group(group: &GroupState) -> Self {
+        let value = |name: &str| group.values.get(name).cloned().flatten();
+        Self {
+            year: value("yr"),
+            month: value("mo"),
+            day: value("dy"),
+            hour: value("hr"),
+            minute: value("min"),
+            second: value("sec"),
+        }
+    }
+}
+
 #[derive(Clone)]
 pub struct DocumentState {
     pub destinations: Rc<RefCell<HashMap<String, Destination>>>,
     pub group_stack: Vec<GroupState>,
-    pub buffer: Vec<u8>,
-    pub fonts: HashMap<i32, Font>,
+    pub fonts: Rc<RefCell<HashMap<i32, Font>>>,
     pub colors: Vec<Color>,
     pub stylesheets: HashMap<i32, StyleSheet>,
     pub default_font_number: Option<i32>,
+    pub defaults: DocumentDefaults,
+    pub encoding: Option<&'static encoding_rs::Encoding>,
+    pub info: DocumentInfo,
+    pub reporter: Rc<RefCell<Reporter>>,
+    pub options: ParseOptions,
 }
 impl DocumentState {
-    pub fn new() -> Self {
+    pub fn new(options: &ParseOptions) -> Self {
         Self {
             destinations: Rc::new(RefCell::new(HashMap::new())),
+            reporter: Rc::new(RefCell::new(Reporter::new(options))),
+            options: options.clone(),
             group_stack: Vec::new(),
-            buffer: vec![],
-            fonts: HashMap::new(),
+            fonts: Rc::new(RefCell::new(HashMap::new())),
             colors: vec![],
             stylesheets: HashMap::new(),
             default_font_number: None,
+            defaults: DocumentDefaults::default(),
+            encoding: None,
+            info: DocumentInfo::default(),
+        }
+    }
+
+    /// First problem found in strict mode
+    pub fn error(&self) -> Option<Errors> {
+        self.reporter
+            .borrow()
+            .error
+            .clone()
+            .map(Errors::ValidationError)
+    }
+
+    /// Flushes pending text and closes any groups left open
+    pub fn end_input(&mut self) {
+        if !self.group_stack.is_empty() {
+            self.report(WarningKind::UnclosedGroup);
+        }
+        while !self.group_stack.is_empty() {
+            self.end_group();
+        }
+    }
+
+    pub fn finish(mut self) -> Document {
+        self.end_input();
+        let mut destinations = std::mem::take(&mut *self.destinations.borrow_mut());
+        let encoding = self
+            .encoding
+            .or(self.options.default_encoding)
+            .unwrap_or(encoding_rs::WINDOWS_1252);
+        for name in DocumentInfo::TEXT_DESTINATIONS.iter() {
+            let value = match destinations.remove(*name) {
+                Some(Destination::Text(text)) => text.to_string(),
+                Some(Destination::Bytes(bytes)) => encoding.decode(&bytes).0.into_owned(),
+                Some(Destination::Skipped(_)) | None => continue,
+            };
+            self.info.set_text(name, value);
+        }
+        let body = match destinations.remove("rtf") {
+            Some(Destination::Text(text)) => Some(text),
+            _ => None,
+        };
+        for name in [
+            "fonttbl",
+            "falt",
+            "panose",
+            "colortbl",
+            "stylesheet",
+            "info",
+            "defchp",
+            "defpap",
+        ]
+        .iter()
+        {
+            destinations.remove(*name);
+        }
+        Document {
+            body,
+            fonts: std::mem::take(&mut *self.fonts.borrow_mut()),
+            stylesheets: self.stylesheets,
+            colors: self.colors,
+            default_font_number: self.default_font_number,
+            defaults: self.defaults,
+            info: self.info,
+            destinations,
+            diagnostics: std::mem::take(&mut self.reporter.borrow_mut().diagnostics),
         }
     }
 
-    pub fn do_control_bin(&mut self, _data: &[u8], _word_is_optional: bool) {
-        println!("not support do conotrol bin");
+    pub fn do_control_bin(&mut self, data: &[u8], _word_is_optional: bool) {
+        if let Some(group_state) = self.get_last_group_mut() {
+            if group_state.skip_fallback() {
+                return;
+            }
+        }
         // We don't support handling control bins
+        self.report(WarningKind::DroppedBinary(data.len()));
     }
 
     pub fn do_control_symbol(&mut self, symbol: char, word_is_optional: bool) {
         let mut sym_bytes = [0; 4];
         let sym_str = symbol.encode_utf8(&mut sym_bytes);
-        if let Some(mut group_state) = self.get_last_group_mut() {
+        if let Some(group_state) = self.get_last_group_mut() {
+            if group_state.skip_fallback() {
+                return;
+            }
             if let Some(symbol_handler) = rtf_control::SYMBOLS.get(sym_str) {
-                symbol_handler(&mut group_state, sym_str, None);
+                symbol_handler(group_state, sym_str, None);
             } else if word_is_optional {
-                warn!("Skipping optional unsupported control word \\{}", symbol);
+                trace!("Skipping optional unsupported control symbol \\{}", symbol);
             } else {
-                warn!(
-                    "Unsupported/illegal control symbol \\{} (writing to document anyway)",
-                    symbol
-                );
+                // writing to document anyway
+                self.report(WarningKind::UnsupportedControlSymbol(symbol));
                 self.write_to_current_destination(format!("{}", symbol).as_bytes());
             }
         } else {
-            warn!(
-                "Document format error: Control symbol found outside of any document group: '\\{}'",
-                symbol
-            );
+            self.report(WarningKind::ContentOutsideDocument);
         }
     }
 
     pub fn do_control_word(&mut self, name: &str, arg: Option<i32>, word_is_optional: bool) {
-        if let Some(mut group_state) = self.get_last_group_mut() {
+        if let Some(group_state) = self.get_last_group_mut() {
+            // pending \'xx bytes belong to the formatting before this word, and
+            // each of them is one fallback character of a preceding \uN
+            if name != "'" && group_state.skip_fallback() {
+                return;
+            }
             if let Some(dest_handler) = rtf_control::DESTINATIONS.get(name) {
-                dest_handler(&mut group_state, name, arg);
+                dest_handler(group_state, name, arg);
             } else if let Some(symbol_handler) = rtf_control::SYMBOLS.get(name) {
-                symbol_handler(&mut group_state, name, arg);
+                symbol_handler(group_state, name, arg);
             } else if let Some(value_handler) = rtf_control::VALUES.get(name) {
-                value_handler(&mut group_state, name, arg);
+                value_handler(group_state, name, arg);
             } else if let Some(flag_handler) = rtf_control::FLAGS.get(name) {
-                flag_handler(&mut group_state, name, arg);
+                flag_handler(group_state, name, arg);
             } else if let Some(toggle_handler) = rtf_control::TOGGLES.get(name) {
-                toggle_handler(&mut group_state, name, arg);
+                toggle_handler(group_state, name, arg);
             } else if word_is_optional {
-                warn!("Skipping optional unsupported control word \\{}", name);
+                // keep the group's content out of the current destination
+                group_state.report(WarningKind::UnsupportedDestination(name.to_string()));
+                group_state.flush();
+                group_state.set_destination(name, false);
             } else {
-                warn!("Unsupported/illegal control word \\{}", name);
+                group_state.report(WarningKind::UnsupportedControlWord(name.to_string()));
             }
         } else {
-            warn!(
-                "Document format error: Control word found outside of any document group: '\\{}'",
-                name
-            );
+            self.report(WarningKind::ContentOutsideDocument);
         }
     }
 
     pub fn write_to_current_destination(&mut self, bytes: &[u8]) {
         if let Some(group) = self.get_last_group_mut() {
+            group.flush();
             group.write(bytes);
         } else {
             // it is a fundamental document formatting error for text to appear outside of the {\rtf1 } group
-            warn!(
-                "Document format error: Document text found outside of any document group: '{:?}'",
-                bytes
-            );
+            self.report(WarningKind::ContentOutsideDocument);
         }
     }
 
     pub fn start_group(&mut self) {
         if let Some(last_group) = self.get_last_group_mut() {
             last_group.flush();
+            // skipping \uN fallback characters stops at group boundaries
+            last_group.ignore_count = 0;
         }
         if let Some(last_group) = self.get_last_group().cloned() {
             self.group_stack.push(last_group.clone());
         } else {
             debug!("Creating initial group...");
-            self.group_stack
-                .push(GroupState::new(self.destinations.clone()));
+            self.group_stack.push(GroupState::new(
+                self.destinations.clone(),
+                self.reporter.clone(),
+                self.fonts.clone(),
+                self.options.default_encoding,
+                self.options.keep_binary_destinations,
+            ));
         }
     }
     pub fn process_colortable(&mut self, group: &mut GroupState) {
@@ -107,18 +395,66 @@ impl DocumentState {
     }
 
     pub fn process_rtf(&mut self, group: &GroupState) {
-        let number = group.values.get("deff").unwrap_or(&None);
-        self.default_font_number = number.clone();
+        let value = |name: &str| group.values.get(name).cloned().flatten();
+        self.default_font_number = value("deff");
+        let defaults = &mut self.defaults;
+        defaults.font = value("deff");
+        defaults.style_fonts = AssociatedFonts {
+            ascii: value("stshfloch"),
+            high_ansi: value("stshfhich"),
+            east_asia: value("stshfdbch"),
+            complex: value("stshfbi").or_else(|| value("adeff")),
+        };
+        defaults.language = value("deflang");
+        defaults.east_asian_language = value("deflangfe");
+        defaults.complex_language = value("adeflang");
+        defaults.tab_width = value("deftab");
+        self.encoding = Some(
+            group
+                .get_encoding()
+                .unwrap_or_else(|| group.fallback_encoding()),
+        );
+    }
+    pub fn process_info(&mut self, group: &GroupState) {
+        let value = |name: &str| group.values.get(name).cloned().flatten();
+        let info = &mut self.info;
+        info.version = value("version").or(info.version);
+        info.editing_minutes = value("edmins").or(info.editing_minutes);
+        info.pages = value("nofpages").or(info.pages);
+        info.words = value("nofwords").or(info.words);
+        info.characters = value("nofchars").or(info.characters);
     }
     pub fn process_font(&mut self, group: &GroupState) {
-        let number = group.values.get("f").unwrap_or(&Some(1)).unwrap_or(1);
-        let charset = group.values.get("fcharset").unwrap_or(&None).clone();
+        // the enclosing {\fonttbl ...} group ends without a font of its own
+        let number = match group.values.get("f") {
+            Some(number) => number.unwrap_or(1),
+            None => return,
+        };
+        let charset = *group.values.get("fcharset").unwrap_or(&None);
         let mut dests = self.destinations.borrow_mut();
-        let tbl = dests.get_mut("fonttbl").expect("font table not exist");
-        if let Destination::Text(text) = tbl {
+        // {\*\falt ...} and {\*\panose ...} of this font, kept out of its name
+        let mut take_text = |name: &str| match dests.get_mut(name) {
+            Some(Destination::Text(text)) => {
+                let value = text.to_string().trim().to_string();
+                text.clear();
+                Some(value).filter(|value| !value.is_empty())
+            }
+            _ => None,
+        };
+        let alt_font_name = take_text("falt");
+        let panose = take_text("panose");
+        if let Some(Destination::Text(text)) = dests.get_mut("fonttbl") {
             let charset = charset.map(|c| Charset::from(c as usize));
-            if Some(Charset::ShiftJIS) == charset {
-                text.encoding = Some(encoding_rs::SHIFT_JIS);
+            let encoding = charset.as_ref().and_then(Charset::encoding);
+            if let (Some(codepage), None) = (charset.as_ref().and_then(Charset::codepage), encoding)
+            {
+                group.report(WarningKind::EncodingFallback {
+                    codepage: Some(codepage),
+                    used: group
+                        .get_encoding()
+                        .unwrap_or(encoding_rs::WINDOWS_1252)
+                        .name(),
+                });
             }
             let font_name = text.to_string().replace(";", "");
 
@@ -128,28 +464,53 @@ impl DocumentState {
                 font_name,
                 charset,
                 family: group.get_font_family(),
-                alt_font_name: None,
-                pitch: None,
+                alt_font_name,
+                pitch: *group.values.get("fprq").unwrap_or(&None),
+                panose,
+                bias: *group.values.get("fbias").unwrap_or(&None),
+                theme: group
+                    .values
+                    .keys()
+                    .find_map(|name| ThemeFont::from_control_word(name)),
             };
-            self.fonts.insert(number, font);
+            self.fonts.borrow_mut().insert(number, font);
         }
     }
     pub fn process_stylesheet(&mut self, group: &GroupState) {
-        let number = group.values.get("s").unwrap_or(&Some(0)).unwrap_or(0);
+        let value = |name: &str| group.values.get(name).cloned();
+        let (kind, number) = if let Some(number) = value("cs") {
+            (StyleKind::Character, number)
+        } else if let Some(number) = value("ts") {
+            (StyleKind::Table, number)
+        } else if let Some(number) = value("ds") {
+            (StyleKind::Section, number)
+        } else {
+            (StyleKind::Paragraph, value("s").flatten())
+        };
         let mut dests = self.destinations.borrow_mut();
-        let tbl = dests.get_mut("fonttbl").expect("font table not exist");
-
-        if let Destination::Text(text) = tbl {
-            let style_name = text.to_string().replace(";", "");
-
+        if let Some(Destination::Text(text)) = dests.get_mut("stylesheet") {
+            let style_name = text.to_string().replace(';', "").trim().to_string();
             text.clear();
+            // the enclosing {\stylesheet ...} group ends without a style of its own
+            if style_name.is_empty() && number.is_none() {
+                return;
+            }
+
+            let number = number.unwrap_or(0);
             let stylesheet = StyleSheet {
                 number,
                 name: style_name,
+                kind,
+                based_on: value("sbasedon").flatten(),
+                next: value("snext").flatten(),
+                link: value("slink").flatten(),
+                q_format: group.has_key("sqformat"),
+                additive: group.has_key("additive"),
+                semi_hidden: group.has_key("ssemihidden"),
+                font: group.char_props.font,
                 font_style: group.get_cur_style(),
                 para_style: group.get_cur_para_style(),
             };
-
             self.stylesheets.insert(number, stylesheet);
         }
     }
@@ -161,24 +522,55 @@ impl DocumentState {
                 "stylesheet" => self.process_stylesheet(group),
                 "colortbl" => self.process_colortable(group),
                 "rtf" => self.process_rtf(group),
+                "info" => self.process_info(group),
+                "defchp" => {
+                    self.defaults.associated_fonts = group.char_props.associated_fonts.clone();
+                    self.defaults.font_style = group.get_cur_style();
+                }
+                "defpap" => self.defaults.para_style = group.get_cur_para_style(),
+                "creatim" => self.info.created = Some(DocumentTime::from_group(group)),
+                "revtim" => self.info.revised = Some(DocumentTime::from_group(group)),
+                "printim" => self.info.printed = Some(DocumentTime::from_group(group)),
+                "buptim" => self.info.backed_up = Some(DocumentTime::from_group(group)),
                 _ => {}
             };
         }
     }
     pub fn end_group(&mut self) {
-        let ignore_count = if let Some(mut group) = self.group_stack.pop() {
+        if let Some(mut group) = self.group_stack.pop() {
             group.flush();
+            group.flush_surrogate();
 
             self.process_group(&mut group);
-            group.ignore_count
+            self.drop_skipped_destination(&group);
         // TODO: destination-folding support (tables, etc)
         } else {
-            warn!("Document format error: End group count exceeds number start groups");
-            0
-        };
+            self.report(WarningKind::UnmatchedEndGroup);
+        }
+    }
 
-        if let Some(last) = self.group_stack.last_mut() {
-            last.ignore_count = ignore_count;
+    /// Reports the data of a skipped destination when the group that opened it ends,
+    /// so that each picture or object is reported once
+    fn drop_skipped_destination(&mut self, group: &GroupState) {
+        let name = match group.cur_destination {
+            Some(ref name) => name,
+            None => return,
+        };
+        if self
+            .get_last_group()
+            .and_then(|g| g.cur_destination.as_ref())
+            == Some(name)
+        {
+            return;
+        }
+        let mut destinations = self.destinations.borrow_mut();
+        if let Some(&Destination::Skipped(len)) = destinations.get(name) {
+            destinations.remove(name);
+            drop(destinations);
+            self.report(WarningKind::DroppedDestination {
+                name: name.clone(),
+                len,
+            });
         }
     }
 
@@ -189,20 +581,21 @@ impl DocumentState {
     pub fn get_last_group(&self) -> Option<&GroupState> {
         self.group_stack.last()
     }
-    fn write_buffer(&mut self, bytes: &Vec<u8>) {
-        // let mut buf = bytes.clone();
-        self.buffer.extend(bytes);
+    fn report(&self, kind: WarningKind) {
+        self.reporter.borrow_mut().report(kind);
     }
-    fn flush_buffer(&mut self) {
-        let buffer = self.buffer.clone();
-
-        if !buffer.is_empty() {
-            self.write_to_current_destination(&buffer);
-
-            self.buffer.clear();
+    pub fn process_token(&mut self, offset: usize, token: &Token) {
+        {
+            let mut reporter = self.reporter.borrow_mut();
+            reporter.location.offset = offset;
+            reporter.location.depth = self.group_stack.len();
+            if let Token::ControlWord { name, .. } = token {
+                if name != "'" {
+                    reporter.location.control_word = Some(name.clone());
+                }
+            }
         }
-    }
-    pub fn process_token(&mut self, token: &Token) {
+
         let word_is_optional = self
             .get_last_group_mut()
             .map(|group| group.get_and_clear_ignore_next_control())
@@ -210,9 +603,13 @@ impl DocumentState {
 
         // Update state for this token
         if let Token::Text(bytes) = token {
-            self.write_buffer(bytes);
+            // buffered with the \'xx bytes around it, so a character split between
+            // the two is decoded whole
+            match self.get_last_group_mut() {
+                Some(group) => group.buffer(bytes),
+                None => self.report(WarningKind::ContentOutsideDocument),
+            }
         } else {
-            self.flush_buffer();
             match token {
                 Token::ControlSymbol(c) => self.do_control_symbol(*c, word_is_optional),
                 Token::ControlWord { name, arg } => {
@@ -1,5 +1,3 @@
-use std::io::SeekFrom;
-
 use super::*;
 use std::collections::HashMap;
 use std::collections::VecDeque;
@@ -10,225 +8,1595 @@ pub trait Docx {
 }
 impl Docx for Rtf {
     fn into_docx_base64(self) -> Result<String, Errors> {
-        let docx = self.into_docx()?;
-        Ok(base64::encode(docx))
+        self.parse().to_docx_base64()
     }
     fn into_docx(self) -> Result<Vec<u8>, Errors> {
-        impl std::convert::Into<AlignmentType> for Align {
-            fn into(self) -> AlignmentType {
-                match self {
-                    Align::Left => AlignmentType::Left,
-                    Align::Justify => AlignmentType::Justified,
-                    Align::Center => AlignmentType::Center,
-                    Align::Right => AlignmentType::Right,
-                }
+        self.parse().to_docx()
+    }
+}
+
+impl From<UnderlineType> for String {
+    fn from(underline: UnderlineType) -> Self {
+        match underline {
+            UnderlineType::None => "none",
+            UnderlineType::Single | UnderlineType::Hairline => "single",
+            UnderlineType::Words => "words",
+            UnderlineType::Double => "double",
+            UnderlineType::Thick => "thick",
+            UnderlineType::Dotted => "dotted",
+            UnderlineType::ThickDotted => "dottedHeavy",
+            UnderlineType::Dash => "dash",
+            UnderlineType::ThickDash => "dashedHeavy",
+            UnderlineType::LongDash => "dashLong",
+            UnderlineType::ThickLongDash => "dashLongHeavy",
+            UnderlineType::DotDash => "dotDash",
+            UnderlineType::ThickDotDash => "dashDotHeavy",
+            UnderlineType::DotDotDash => "dotDotDash",
+            UnderlineType::ThickDotDotDash => "dashDotDotHeavy",
+            UnderlineType::Wave => "wave",
+            UnderlineType::HeavyWave => "wavyHeavy",
+            UnderlineType::DoubleWave => "wavyDouble",
+        }
+        .to_owned()
+    }
+}
+
+impl From<Align> for docx_rs::AlignmentType {
+    fn from(align: Align) -> Self {
+        match align {
+            Align::Left => docx_rs::AlignmentType::Left,
+            Align::Justify => docx_rs::AlignmentType::Justified,
+            Align::Center => docx_rs::AlignmentType::Center,
+            Align::Right => docx_rs::AlignmentType::Right,
+        }
+    }
+}
+impl From<CellVerticalAlignment> for docx_rs::VAlignType {
+    fn from(align: CellVerticalAlignment) -> Self {
+        match align {
+            CellVerticalAlignment::Top => docx_rs::VAlignType::Top,
+            CellVerticalAlignment::Center => docx_rs::VAlignType::Center,
+            CellVerticalAlignment::Bottom => docx_rs::VAlignType::Bottom,
+        }
+    }
+}
+impl From<table_border::BorderType> for docx_rs::BorderType {
+    fn from(border_type: table_border::BorderType) -> Self {
+        match border_type {
+            table_border::BorderType::SingleThickness => docx_rs::BorderType::Single,
+            table_border::BorderType::DoubleThickness => docx_rs::BorderType::Thick,
+            table_border::BorderType::Shadowed => docx_rs::BorderType::Single,
+            table_border::BorderType::Double => docx_rs::BorderType::Double,
+            table_border::BorderType::Dotted => docx_rs::BorderType::Dotted,
+            table_border::BorderType::Dashed => docx_rs::BorderType::Dashed,
+            table_border::BorderType::Hairline => docx_rs::BorderType::Single,
+            table_border::BorderType::Inset => docx_rs::BorderType::Inset,
+            table_border::BorderType::Outset => docx_rs::BorderType::Outset,
+            table_border::BorderType::DashSmall => docx_rs::BorderType::DashSmallGap,
+            table_border::BorderType::DotDash => docx_rs::BorderType::DotDash,
+            table_border::BorderType::DotDotDash => docx_rs::BorderType::DotDotDash,
+            table_border::BorderType::Triple => docx_rs::BorderType::Triple,
+            table_border::BorderType::ThickThinSmall => docx_rs::BorderType::ThickThinSmallGap,
+            table_border::BorderType::ThinThickSmall => docx_rs::BorderType::ThinThickSmallGap,
+            table_border::BorderType::ThinThickThinSmall => {
+                docx_rs::BorderType::ThinThickThinSmallGap
+            }
+            table_border::BorderType::ThickThinMedium => docx_rs::BorderType::ThickThinMediumGap,
+            table_border::BorderType::ThinThickMedium => docx_rs::BorderType::ThinThickMediumGap,
+            table_border::BorderType::ThinThickThinMedium => {
+                docx_rs::BorderType::ThinThickThinMediumGap
             }
+            table_border::BorderType::ThickThinLarge => docx_rs::BorderType::ThickThinLargeGap,
+            table_border::BorderType::ThinThickLarge => docx_rs::BorderType::ThinThickLargeGap,
+            table_border::BorderType::ThinThickThinLarge => {
+                docx_rs::BorderType::ThinThickThinLargeGap
+            }
+            table_border::BorderType::Wavy => docx_rs::BorderType::Wave,
+            table_border::BorderType::DoubleWavy => docx_rs::BorderType::DoubleWave,
+            table_border::BorderType::Striped => docx_rs::BorderType::DashDotStroked,
+            table_border::BorderType::Emboss => docx_rs::BorderType::ThreeDEmboss,
+            table_border::BorderType::Engrave => docx_rs::BorderType::ThreeDEngrave,
+            // frames and shadows are attributes of a single border, see `border_attributes`
+            table_border::BorderType::Frame => docx_rs::BorderType::Single,
+            // docx-rs knows only the first few art borders, `border_value` has them all
+            table_border::BorderType::Art(1) => docx_rs::BorderType::Apples,
+            table_border::BorderType::Art(2) => docx_rs::BorderType::ArchedScallops,
+            table_border::BorderType::Art(3) => docx_rs::BorderType::BabyPacifier,
+            table_border::BorderType::Art(4) => docx_rs::BorderType::BabyRattle,
+            table_border::BorderType::Art(_) => docx_rs::BorderType::Single,
+            table_border::BorderType::None => docx_rs::BorderType::None,
         }
-        impl std::convert::Into<docx_rs::VAlignType> for CellVerticalAlignment {
-            fn into(self) -> docx_rs::VAlignType {
-                match self {
-                    Self::Top => VAlignType::Top,
-                    Self::Center => VAlignType::Center,
-                    Self::Bottom => VAlignType::Bottom,
-                }
+    }
+}
+/// Art borders in `\brdrartN` order, from 1
+const ART_BORDERS: [&str; 165] = [
+    "apples",
+    "archedScallops",
+    "babyPacifier",
+    "babyRattle",
+    "balloons3Colors",
+    "balloonsHotAir",
+    "basicBlackDashes",
+    "basicBlackDots",
+    "basicBlackSquares",
+    "basicThinLines",
+    "basicWhiteDashes",
+    "basicWhiteDots",
+    "basicWhiteSquares",
+    "basicWideInline",
+    "basicWideMidline",
+    "basicWideOutline",
+    "bats",
+    "birds",
+    "birdsFlight",
+    "cabins",
+    "cakeSlice",
+    "candyCorn",
+    "celticKnotwork",
+    "certificateBanner",
+    "chainLink",
+    "champagneBottle",
+    "checkedBarBlack",
+    "checkedBarColor",
+    "checkered",
+    "christmasTree",
+    "circlesLines",
+    "circlesRectangles",
+    "classicalWave",
+    "clocks",
+    "compass",
+    "confetti",
+    "confettiGrays",
+    "confettiOutline",
+    "confettiStreamers",
+    "confettiWhite",
+    "cornerTriangles",
+    "couponCutoutDashes",
+    "couponCutoutDots",
+    "crazyMaze",
+    "creaturesButterfly",
+    "creaturesFish",
+    "creaturesInsects",
+    "creaturesLadyBug",
+    "crossStitch",
+    "cup",
+    "decoArch",
+    "decoArchColor",
+    "decoBlocks",
+    "diamondsGray",
+    "doubleD",
+    "doubleDiamonds",
+    "earth1",
+    "earth2",
+    "earth3",
+    "eclipsingSquares1",
+    "eclipsingSquares2",
+    "eggsBlack",
+    "fans",
+    "film",
+    "firecrackers",
+    "flowersBlockPrint",
+    "flowersDaisies",
+    "flowersModern1",
+    "flowersModern2",
+    "flowersPansy",
+    "flowersRedRose",
+    "flowersRoses",
+    "flowersTeacup",
+    "flowersTiny",
+    "gems",
+    "gingerbreadMan",
+    "gradient",
+    "handmade1",
+    "handmade2",
+    "heartBalloon",
+    "heartGray",
+    "hearts",
+    "heebieJeebies",
+    "holly",
+    "houseFunky",
+    "hypnotic",
+    "iceCreamCones",
+    "lightBulb",
+    "lightning1",
+    "lightning2",
+    "mapPins",
+    "mapleLeaf",
+    "mapleMuffins",
+    "marquee",
+    "marqueeToothed",
+    "moons",
+    "mosaic",
+    "musicNotes",
+    "northwest",
+    "ovals",
+    "packages",
+    "palmsBlack",
+    "palmsColor",
+    "paperClips",
+    "papyrus",
+    "partyFavor",
+    "partyGlass",
+    "pencils",
+    "people",
+    "peopleWaving",
+    "peopleHats",
+    "poinsettias",
+    "postageStamp",
+    "pumpkin1",
+    "pushPinNote2",
+    "pushPinNote1",
+    "pyramids",
+    "pyramidsAbove",
+    "quadrants",
+    "rings",
+    "safari",
+    "sawtooth",
+    "sawtoothGray",
+    "scaredCat",
+    "seattle",
+    "shadowedSquares",
+    "sharksTeeth",
+    "shorebirdTracks",
+    "skyrocket",
+    "snowflakeFancy",
+    "snowflakes",
+    "sombrero",
+    "southwest",
+    "stars",
+    "starsTop",
+    "stars3d",
+    "starsBlack",
+    "starsShadowed",
+    "sun",
+    "swirligig",
+    "tornPaper",
+    "tornPaperBlack",
+    "trees",
+    "triangleParty",
+    "triangles",
+    "triangle1",
+    "triangle2",
+    "triangleCircle1",
+    "triangleCircle2",
+    "shapes1",
+    "shapes2",
+    "twistedLines1",
+    "twistedLines2",
+    "vine",
+    "waveline",
+    "weavingAngles",
+    "weavingBraid",
+    "weavingRibbon",
+    "weavingStrips",
+    "whiteFlowers",
+    "woodwork",
+    "xIllusions",
+    "zanyTriangles",
+    "zigZag",
+    "zigZagStitch",
+];
+/// w:val of a border
+fn border_value(border_type: &table_border::BorderType) -> String {
+    if let table_border::BorderType::Art(number) = border_type {
+        let art = number.checked_sub(1).filter(|index| *index >= 0);
+        if let Some(art) = art.and_then(|index| ART_BORDERS.get(index as usize)) {
+            return art.to_string();
+        }
+    }
+    docx_rs::BorderType::from(border_type.clone()).to_string()
+}
+/// w:sz of a border, in eighths of a point
+fn border_size(border: &Border) -> usize {
+    border.width * 2 / 5
+}
+/// All attributes of a border element; w:sz is in eighths of a point and w:space in points
+fn border_attributes(border: &Border, color_table: &[color::Color]) -> String {
+    let effect = match border.border_type {
+        table_border::BorderType::Shadowed => r#" w:shadow="1""#,
+        table_border::BorderType::Frame => r#" w:frame="1""#,
+        _ => "",
+    };
+    format!(
+        r#"w:val="{}" w:sz="{}" w:space="{}" w:color="{}"{}"#,
+        border_value(&border.border_type),
+        border_size(border),
+        border.space / 20,
+        color_value(border.color, color_table),
+        effect
+    )
+}
+/// A borders element such as `w:pBdr`, with the sides in the order given
+fn borders_xml(
+    name: &str,
+    sides: &[(&str, &Option<Border>)],
+    color_table: &[color::Color],
+) -> Option<String> {
+    let sides: String = sides
+        .iter()
+        .filter_map(|(side, border)| {
+            let attributes = border_attributes(border.as_ref()?, color_table);
+            Some(format!("<w:{} {} />", side, attributes))
+        })
+        .collect();
+    if sides.is_empty() {
+        None
+    } else {
+        Some(format!("<{0}>{1}</{0}>", name, sides))
+    }
+}
+fn table_borders(border: &RowBorder, color_table: &[color::Color]) -> docx_rs::TableBorders {
+    use docx_rs::{TableBorder, TableBorderPosition};
+    let sides = [
+        (&border.top, TableBorderPosition::Top),
+        (&border.left, TableBorderPosition::Left),
+        (&border.right, TableBorderPosition::Right),
+        (&border.bottom, TableBorderPosition::Bottom),
+        (&border.vertical, TableBorderPosition::InsideV),
+        (&border.horizontal, TableBorderPosition::InsideH),
+    ];
+    let mut borders = docx_rs::TableBorders::new();
+    for (side, position) in sides.iter() {
+        if let Some(side) = side {
+            let b = TableBorder::new(position.clone())
+                .border_type(side.border_type.clone().into())
+                .size(border_size(side))
+                .color(color_value(side.color, color_table));
+            borders = borders.set(b);
+        }
+    }
+    borders
+}
+/// `w:tblBorders` of a row, with the border spacing docx-rs keeps private
+fn table_borders_xml(border: &RowBorder, color_table: &[color::Color]) -> Option<String> {
+    let sides = [
+        ("top", &border.top),
+        ("left", &border.left),
+        ("bottom", &border.bottom),
+        ("right", &border.right),
+        ("insideH", &border.horizontal),
+        ("insideV", &border.vertical),
+    ];
+    borders_xml("w:tblBorders", &sides, color_table)
+}
+fn table_cell_borders(
+    border: &CellBorder,
+    color_table: &[color::Color],
+) -> docx_rs::TableCellBorders {
+    use docx_rs::{TableCellBorder, TableCellBorderPosition};
+    let sides = [
+        (&border.top, TableCellBorderPosition::Top),
+        (&border.left, TableCellBorderPosition::Left),
+        (&border.right, TableCellBorderPosition::Right),
+        (&border.bottom, TableCellBorderPosition::Bottom),
+    ];
+    let mut borders = docx_rs::TableCellBorders::new();
+    for (side, position) in sides.iter() {
+        if let Some(side) = side {
+            let b = TableCellBorder::new(position.clone())
+                .border_type(side.border_type.clone().into())
+                .size(border_size(side))
+                .color(color_value(side.color, color_table));
+            borders = borders.set(b);
+        }
+    }
+    borders
+}
+/// `w:pgBorders` of a section
+fn page_borders_xml(page_border: &PageBorder, color_table: &[color::Color]) -> Option<String> {
+    let sides = [
+        ("top", &page_border.top),
+        ("left", &page_border.left),
+        ("bottom", &page_border.bottom),
+        ("right", &page_border.right),
+    ];
+    borders_xml("w:pgBorders", &sides, color_table)
+}
+/// `w:tcBorders` of a cell, with the spacing written as for rows
+fn table_cell_borders_xml(border: &CellBorder, color_table: &[color::Color]) -> Option<String> {
+    let sides = [
+        ("top", &border.top),
+        ("left", &border.left),
+        ("bottom", &border.bottom),
+        ("right", &border.right),
+    ];
+    borders_xml("w:tcBorders", &sides, color_table)
+}
+impl From<RowBorder> for docx_rs::TableBorders {
+    fn from(border: RowBorder) -> Self {
+        table_borders(&border, &[])
+    }
+}
+impl From<CellBorder> for docx_rs::TableCellBorders {
+    fn from(border: CellBorder) -> Self {
+        table_cell_borders(&border, &[])
+    }
+}
+
+/// Style id of the default paragraph style, which docx-rs also writes on its own
+const NORMAL_STYLE_ID: &str = "Normal";
+
+fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Child elements of `w:rPr` in schema order
+const RUN_PROPERTY_ORDER: [&str; 39] = [
+    "w:rStyle",
+    "w:rFonts",
+    "w:b",
+    "w:bCs",
+    "w:i",
+    "w:iCs",
+    "w:caps",
+    "w:smallCaps",
+    "w:strike",
+    "w:dstrike",
+    "w:outline",
+    "w:shadow",
+    "w:emboss",
+    "w:imprint",
+    "w:noProof",
+    "w:snapToGrid",
+    "w:vanish",
+    "w:webHidden",
+    "w:color",
+    "w:spacing",
+    "w:w",
+    "w:kern",
+    "w:position",
+    "w:sz",
+    "w:szCs",
+    "w:highlight",
+    "w:u",
+    "w:effect",
+    "w:bdr",
+    "w:shd",
+    "w:fitText",
+    "w:vertAlign",
+    "w:rtl",
+    "w:cs",
+    "w:em",
+    "w:lang",
+    "w:eastAsianLayout",
+    "w:specVanish",
+    "w:oMath",
+];
+/// Child elements of `w:pPr` in schema order
+const PARAGRAPH_PROPERTY_ORDER: [&str; 36] = [
+    "w:pStyle",
+    "w:keepNext",
+    "w:keepLines",
+    "w:pageBreakBefore",
+    "w:framePr",
+    "w:widowControl",
+    "w:numPr",
+    "w:suppressLineNumbers",
+    "w:pBdr",
+    "w:shd",
+    "w:tabs",
+    "w:suppressAutoHyphens",
+    "w:kinsoku",
+    "w:wordWrap",
+    "w:overflowPunct",
+    "w:topLinePunct",
+    "w:autoSpaceDE",
+    "w:autoSpaceDN",
+    "w:bidi",
+    "w:adjustRightInd",
+    "w:snapToGrid",
+    "w:spacing",
+    "w:ind",
+    "w:contextualSpacing",
+    "w:mirrorIndents",
+    "w:suppressOverlap",
+    "w:jc",
+    "w:textDirection",
+    "w:textAlignment",
+    "w:textboxTightWrap",
+    "w:outlineLvl",
+    "w:divId",
+    "w:cnfStyle",
+    "w:rPr",
+    "w:sectPr",
+    "w:pPrChange",
+];
+/// Child elements of `w:tblPr` in schema order
+const TABLE_PROPERTY_ORDER: [&str; 17] = [
+    "w:tblStyle",
+    "w:tblpPr",
+    "w:tblOverlap",
+    "w:bidiVisual",
+    "w:tblStyleRowBandSize",
+    "w:tblStyleColBandSize",
+    "w:tblW",
+    "w:jc",
+    "w:tblCellSpacing",
+    "w:tblInd",
+    "w:tblBorders",
+    "w:shd",
+    "w:tblLayout",
+    "w:tblCellMar",
+    "w:tblLook",
+    "w:tblCaption",
+    "w:tblDescription",
+];
+/// Child elements of `w:tcPr` in schema order
+const CELL_PROPERTY_ORDER: [&str; 13] = [
+    "w:cnfStyle",
+    "w:tcW",
+    "w:gridSpan",
+    "w:hMerge",
+    "w:vMerge",
+    "w:tcBorders",
+    "w:shd",
+    "w:noWrap",
+    "w:tcMar",
+    "w:textDirection",
+    "w:tcFitText",
+    "w:vAlign",
+    "w:hideMark",
+];
+/// Child elements of `w:sectPr` in schema order
+const SECTION_PROPERTY_ORDER: [&str; 22] = [
+    "w:headerReference",
+    "w:footerReference",
+    "w:footnotePr",
+    "w:endnotePr",
+    "w:type",
+    "w:pgSz",
+    "w:pgMar",
+    "w:paperSrc",
+    "w:pgBorders",
+    "w:lnNumType",
+    "w:pgNumType",
+    "w:cols",
+    "w:formProt",
+    "w:vAlign",
+    "w:noEndnote",
+    "w:titlePg",
+    "w:textDirection",
+    "w:bidi",
+    "w:rtlGutter",
+    "w:docGrid",
+    "w:printerSettings",
+    "w:sectPrChange",
+];
+
+#[derive(Clone, Copy, PartialEq)]
+enum TagKind {
+    Open,
+    Close,
+    Empty,
+}
+/// A tag of the XML docx-rs writes, and where it is
+struct XmlTag<'a> {
+    name: &'a str,
+    kind: TagKind,
+    start: usize,
+    end: usize,
+}
+/// The tags of XML written by docx-rs, skipping the declaration and text
+struct XmlTags<'a> {
+    xml: &'a str,
+    at: usize,
+}
+impl<'a> XmlTags<'a> {
+    fn new(xml: &'a str) -> Self {
+        Self { xml, at: 0 }
+    }
+    /// End of the element whose start tag came last, after its end tag
+    fn element_end(&mut self) -> usize {
+        let mut depth = 1;
+        for tag in self.by_ref() {
+            match tag.kind {
+                TagKind::Open => depth += 1,
+                TagKind::Close if depth == 1 => return tag.end,
+                TagKind::Close => depth -= 1,
+                TagKind::Empty => {}
             }
         }
-        impl std::convert::Into<docx_rs::BorderType> for table_border::BorderType {
-            fn into(self) -> docx_rs::BorderType {
-                match self {
-                    table_border::BorderType::SingleThickness => docx_rs::BorderType::Single,
-                    table_border::BorderType::DoubleThickness => docx_rs::BorderType::Double,
-                    table_border::BorderType::Shadowed => docx_rs::BorderType::Single,
-                    table_border::BorderType::Double => docx_rs::BorderType::Double,
-                    table_border::BorderType::Dotted => docx_rs::BorderType::Dotted,
-                    table_border::BorderType::Dashed => docx_rs::BorderType::Dashed,
-                    table_border::BorderType::Hairline => docx_rs::BorderType::Single,
-                    table_border::BorderType::None => docx_rs::BorderType::None,
-                    //_ => docx_rs::BorderType::Single,
+        self.xml.len()
+    }
+}
+impl<'a> Iterator for XmlTags<'a> {
+    type Item = XmlTag<'a>;
+    fn next(&mut self) -> Option<XmlTag<'a>> {
+        loop {
+            let start = self.at + self.xml[self.at..].find('<')?;
+            // the first > outside of attribute values
+            let mut quote = None;
+            let length = self.xml.as_bytes()[start..].iter().position(|&c| {
+                match quote {
+                    Some(q) if c == q => quote = None,
+                    Some(_) => {}
+                    None if c == b'"' || c == b'\'' => quote = Some(c),
+                    None => return c == b'>',
                 }
+                false
+            })?;
+            let end = start + length + 1;
+            self.at = end;
+            let tag = &self.xml[start..end];
+            if tag.starts_with("<?") || tag.starts_with("<!") {
+                continue;
             }
+            let (kind, name) = match tag.strip_prefix("</") {
+                Some(name) => (TagKind::Close, name),
+                None if tag.ends_with("/>") => (TagKind::Empty, &tag[1..]),
+                None => (TagKind::Open, &tag[1..]),
+            };
+            let name_end = name
+                .find(|c: char| c.is_whitespace() || c == '/' || c == '>')
+                .unwrap_or(name.len());
+            return Some(XmlTag {
+                name: &name[..name_end],
+                kind,
+                start,
+                end,
+            });
         }
-        impl std::convert::Into<docx_rs::TableBorders> for RowBorder {
-            fn into(self) -> TableBorders {
-                let mut borders = TableBorders::new();
-                if let Some(top) = self.top {
-                    let mut b = TableBorder::new(docx_rs::TableBorderPosition::Top);
-                    b.border_type = top.border_type.into();
-                    b.size = top.width;
-                    borders = borders.set(b);
-                }
-                if let Some(left) = self.left {
-                    let mut b = TableBorder::new(docx_rs::TableBorderPosition::Left);
-                    b.border_type = left.border_type.into();
-                    b.size = left.width;
-                    borders = borders.set(b);
-                }
-                if let Some(right) = self.right {
-                    let mut b = TableBorder::new(docx_rs::TableBorderPosition::Right);
-                    b.border_type = right.border_type.into();
-                    b.size = right.width;
+    }
+}
+/// Attribute names and (escaped) values of a start tag
+fn xml_attributes(tag: &str) -> Vec<(&str, &str)> {
+    let mut attributes = vec![];
+    let mut rest = tag
+        .trim_start_matches('<')
+        .trim_end_matches('>')
+        .trim_end_matches('/');
+    rest = rest.trim_start_matches(|c: char| !c.is_whitespace());
+    while let Some(equals) = rest.find('=') {
+        let name = rest[..equals].trim();
+        let value = rest[equals + 1..].trim_start();
+        let quote = match value.chars().next() {
+            Some(quote) => quote,
+            None => break,
+        };
+        let value = &value[1..];
+        let value_end = value.find(quote).unwrap_or(value.len());
+        attributes.push((name, &value[..value_end]));
+        rest = value.get(value_end + 1..).unwrap_or("");
+    }
+    attributes
+}
+/// Names and XML of the child elements of `element`
+fn child_elements(element: &str) -> Vec<(&str, &str)> {
+    let mut tags = XmlTags::new(element);
+    let mut children = vec![];
+    if tags.next().is_some_and(|tag| tag.kind == TagKind::Open) {
+        while let Some(tag) = tags.next() {
+            let end = match tag.kind {
+                TagKind::Open => tags.element_end(),
+                TagKind::Empty => tag.end,
+                TagKind::Close => break,
+            };
+            children.push((tag.name, &element[tag.start..end]));
+        }
+    }
+    children
+}
+/// `element` with the attributes of the empty element `addition` set on it
+fn merge_attributes(element: &str, addition: &str) -> String {
+    let mut attributes = xml_attributes(element);
+    for (name, value) in xml_attributes(addition) {
+        match attributes
+            .iter_mut()
+            .find(|(existing, _)| *existing == name)
+        {
+            Some(attribute) => attribute.1 = value,
+            None => attributes.push((name, value)),
+        }
+    }
+    let name = XmlTags::new(element).next().map_or("", |tag| tag.name);
+    let attributes: String = attributes
+        .iter()
+        .map(|(name, value)| format!(r#" {}="{}""#, name, value))
+        .collect();
+    format!("<{}{} />", name, attributes)
+}
+/// A property element like `w:rPr` with the child elements in `additions` merged in:
+/// an empty element adds its attributes to the child of the same name, any other one
+/// replaces it. The children end up in schema order.
+fn merge_children(element: &str, additions: &[String]) -> String {
+    let start = match XmlTags::new(element).next() {
+        Some(tag) => tag,
+        None => return element.to_owned(),
+    };
+    let mut children: Vec<(&str, String)> = child_elements(element)
+        .into_iter()
+        .map(|(name, child)| (name, child.to_owned()))
+        .collect();
+    for addition in additions {
+        let tag = match XmlTags::new(addition).next() {
+            Some(tag) => tag,
+            None => continue,
+        };
+        match children.iter_mut().find(|(name, _)| *name == tag.name) {
+            Some((_, child)) if tag.kind == TagKind::Empty && child.ends_with("/>") => {
+                *child = merge_attributes(child, addition)
+            }
+            Some((_, child)) => *child = addition.clone(),
+            None => children.push((tag.name, addition.clone())),
+        }
+    }
+    let order: &[&str] = match start.name {
+        "w:rPr" => &RUN_PROPERTY_ORDER,
+        "w:pPr" => &PARAGRAPH_PROPERTY_ORDER,
+        "w:tblPr" => &TABLE_PROPERTY_ORDER,
+        "w:tcPr" => &CELL_PROPERTY_ORDER,
+        "w:sectPr" => &SECTION_PROPERTY_ORDER,
+        _ => &[],
+    };
+    children.sort_by_key(|(name, _)| {
+        order
+            .iter()
+            .position(|known| known == name)
+            .unwrap_or(order.len())
+    });
+    let start_tag = element[start.start..start.end]
+        .trim_end_matches('>')
+        .trim_end_matches('/')
+        .trim_end();
+    if children.is_empty() {
+        return format!("{} />", start_tag);
+    }
+    let children: String = children.into_iter().map(|(_, child)| child).collect();
+    format!("{}>{}</{}>", start_tag, children, start.name)
+}
+/// The child elements of a property element docx-rs builds, to merge into another one
+fn property_children<T: docx_rs::BuildXML>(property: &T) -> Vec<String> {
+    let xml = String::from_utf8_lossy(&property.build()).into_owned();
+    child_elements(&xml)
+        .into_iter()
+        .map(|(_, child)| child.to_owned())
+        .collect()
+}
+/// Copies XML written by docx-rs, merging into each element the child elements
+/// `additions` returns for it; `additions` gets the start tag and the names of the
+/// elements it is in
+fn merge_xml<F>(xml: &str, mut additions: F) -> String
+where
+    F: FnMut(&XmlTag, &str, &[&str]) -> Vec<String>,
+{
+    let mut merged = String::with_capacity(xml.len());
+    let mut copied = 0;
+    let mut parents: Vec<&str> = vec![];
+    let mut tags = XmlTags::new(xml);
+    while let Some(tag) = tags.next() {
+        if tag.kind == TagKind::Close {
+            parents.pop();
+            continue;
+        }
+        let added = additions(&tag, &xml[tag.start..tag.end], &parents);
+        if !added.is_empty() {
+            let end = match tag.kind {
+                TagKind::Open => tags.element_end(),
+                _ => tag.end,
+            };
+            merged.push_str(&xml[copied..tag.start]);
+            merged.push_str(&merge_children(&xml[tag.start..end], &added));
+            copied = end;
+        } else if tag.kind == TagKind::Open {
+            parents.push(tag.name);
+        }
+    }
+    merged.push_str(&xml[copied..]);
+    merged
+}
 
-                    borders = borders.set(b);
-                }
-                if let Some(bottom) = self.bottom {
-                    let mut b = TableBorder::new(docx_rs::TableBorderPosition::Bottom);
-                    b.border_type = bottom.border_type.into();
-                    b.size = bottom.width;
-                    borders = borders.set(b);
-                }
-                if let Some(vertical) = self.vertical {
-                    let mut b = TableBorder::new(docx_rs::TableBorderPosition::InsideV);
-                    b.border_type = vertical.border_type.into();
-                    b.size = vertical.width;
-                    borders = borders.set(b);
-                }
-                if let Some(horizontal) = self.horizontal {
-                    let mut b = TableBorder::new(docx_rs::TableBorderPosition::InsideH);
-                    b.border_type = horizontal.border_type.into();
-                    b.size = horizontal.width;
-                    borders = borders.set(b);
+/// Additions to a paragraph, its `w:pPr` and the `w:rPr` of each run
+#[derive(Default)]
+struct ParagraphAdditions {
+    property: Vec<String>,
+    runs: VecDeque<Vec<String>>,
+}
+/// Additions to the `w:rPr` and `w:pPr` of a style
+#[derive(Default)]
+struct StyleAdditions {
+    run: Vec<String>,
+    paragraph: Vec<String>,
+}
+/// What docx-rs has no builders for, like `w:smallCaps`, underline colors and border art,
+/// as child elements to merge into the properties it writes. Paragraphs are found by their
+/// `w14:paraId` and styles by their id; runs, tables and cells by the order they come in.
+#[derive(Default)]
+struct Additions {
+    paragraphs: HashMap<String, ParagraphAdditions>,
+    tables: VecDeque<Vec<String>>,
+    cells: VecDeque<Vec<String>>,
+    section: Vec<String>,
+    styles: HashMap<String, StyleAdditions>,
+    defaults: StyleAdditions,
+    /// Whether the stylesheet has its own Normal style, to take the place of the
+    /// empty one docx-rs always writes ahead of the others
+    replaces_normal: bool,
+}
+impl Additions {
+    fn add_paragraph(&mut self, id: &str, additions: ParagraphAdditions) {
+        if !additions.property.is_empty() || additions.runs.iter().any(|run| !run.is_empty()) {
+            self.paragraphs.insert(id.to_owned(), additions);
+        }
+    }
+    /// `word/document.xml` with the additions merged in
+    fn merge_document(&mut self, xml: &[u8]) -> Vec<u8> {
+        let xml = String::from_utf8_lossy(xml);
+        let mut paragraph: Option<ParagraphAdditions> = None;
+        let merged = merge_xml(&xml, |tag, tag_xml, parents| {
+            let parent = parents.last().copied();
+            match (tag.name, parent) {
+                ("w:p", _) => {
+                    if let Some(done) = paragraph.take() {
+                        debug_assert!(done.runs.is_empty(), "runs of a paragraph not found");
+                    }
+                    paragraph = xml_attributes(tag_xml)
+                        .into_iter()
+                        .find(|(name, _)| *name == "w14:paraId")
+                        .and_then(|(_, id)| self.paragraphs.remove(id));
+                    vec![]
                 }
-                borders
+                ("w:pPr", Some("w:p")) => paragraph
+                    .as_mut()
+                    .map(|paragraph| std::mem::take(&mut paragraph.property))
+                    .unwrap_or_default(),
+                ("w:rPr", Some("w:r")) => paragraph
+                    .as_mut()
+                    .and_then(|paragraph| paragraph.runs.pop_front())
+                    .unwrap_or_default(),
+                ("w:tblPr", Some("w:tbl")) => self.tables.pop_front().unwrap_or_default(),
+                ("w:tcPr", Some("w:tc")) => self.cells.pop_front().unwrap_or_default(),
+                ("w:sectPr", Some("w:body")) => std::mem::take(&mut self.section),
+                _ => vec![],
+            }
+        });
+        debug_assert!(
+            self.paragraphs.is_empty() && self.tables.is_empty() && self.cells.is_empty(),
+            "elements for the DOCX additions not found"
+        );
+        merged.into_bytes()
+    }
+    /// `word/styles.xml` with the additions merged in
+    fn merge_styles(&mut self, xml: &[u8]) -> Vec<u8> {
+        let mut xml = String::from_utf8_lossy(xml).into_owned();
+        if self.replaces_normal {
+            let mut tags = XmlTags::new(&xml);
+            let builtin = tags
+                .find(|tag| {
+                    tag.name == "w:style"
+                        && xml_attributes(&xml[tag.start..tag.end])
+                            .contains(&("w:styleId", NORMAL_STYLE_ID))
+                })
+                .map(|tag| tag.start..tags.element_end());
+            debug_assert!(builtin.is_some(), "docx-rs Normal style not found");
+            if let Some(range) = builtin {
+                xml.replace_range(range, "");
             }
         }
-        impl std::convert::Into<docx_rs::TableCellBorders> for CellBorder {
-            fn into(self) -> TableCellBorders {
-                let mut borders = TableCellBorders::new();
-                if let Some(top) = self.top {
-                    let mut b = TableCellBorder::new(docx_rs::TableCellBorderPosition::Top);
-                    b.border_type = top.border_type.into();
-                    b.size = top.width;
-                    borders = borders.set(b);
+        let mut style: Option<StyleAdditions> = None;
+        let merged = merge_xml(&xml, |tag, tag_xml, parents| {
+            match (tag.name, parents.last().copied()) {
+                ("w:rPr", Some("w:rPrDefault")) => std::mem::take(&mut self.defaults.run),
+                ("w:pPr", Some("w:pPrDefault")) => std::mem::take(&mut self.defaults.paragraph),
+                ("w:style", _) => {
+                    style = xml_attributes(tag_xml)
+                        .into_iter()
+                        .find(|(name, _)| *name == "w:styleId")
+                        .and_then(|(_, id)| self.styles.remove(id));
+                    vec![]
                 }
-                if let Some(left) = self.left {
-                    let mut b = TableCellBorder::new(docx_rs::TableCellBorderPosition::Left);
-                    b.border_type = left.border_type.into();
-                    b.size = left.width;
-                    borders = borders.set(b);
-                }
-                if let Some(right) = self.right {
-                    let mut b = TableCellBorder::new(docx_rs::TableCellBorderPosition::Right);
-                    b.border_type = right.border_type.into();
-                    b.size = right.width;
+                ("w:rPr", Some("w:style")) => style
+                    .as_mut()
+                    .map(|style| std::mem::take(&mut style.run))
+                    .unwrap_or_default(),
+                ("w:pPr", Some("w:style")) => style
+                    .as_mut()
+                    .map(|style| std::mem::take(&mut style.paragraph))
+                    .unwrap_or_default(),
+                _ => vec![],
+            }
+        });
+        debug_assert!(
+            self.styles.is_empty(),
+            "styles for the DOCX additions not found"
+        );
+        merged.into_bytes()
+    }
+}
 
-                    borders = borders.set(b);
-                }
-                if let Some(bottom) = self.bottom {
-                    let mut b = TableCellBorder::new(docx_rs::TableCellBorderPosition::Bottom);
-                    b.border_type = bottom.border_type.into();
-                    b.size = bottom.width;
-                    borders = borders.set(b);
-                }
-                borders
+/// What `run_property` can't build for a `FontStyle`
+fn run_additions(style: &FontStyle, color_table: &[color::Color]) -> Vec<String> {
+    let toggles = [
+        ("smallCaps", style.small_caps),
+        ("outline", style.outline),
+        ("shadow", style.shadow),
+        ("emboss", style.emboss),
+        ("imprint", style.imprint),
+        // docx-rs writes w:vanish but can't turn it off
+        ("vanish", style.hidden.filter(|hidden| !hidden)),
+    ];
+    let mut additions = vec![];
+    for (name, value) in toggles.iter() {
+        match value {
+            Some(true) => additions.push(format!("<w:{} />", name)),
+            Some(false) => additions.push(format!(r#"<w:{} w:val="false" />"#, name)),
+            None => {}
+        }
+    }
+    if let (Some(_), Some(color)) = (style.underline, style.underline_color) {
+        additions.push(format!(
+            r#"<w:u w:color="{}" />"#,
+            color_value(color, color_table)
+        ));
+    }
+    // \up and \dn are in half-points like w:position
+    if let Some(offset) = style.offset {
+        additions.push(format!(r#"<w:position w:val="{}" />"#, offset));
+    }
+    additions
+}
+
+/// Run properties for a `FontStyle`, used for both direct formatting and styles
+fn run_property(style: &FontStyle, color_table: &[color::Color]) -> docx_rs::RunProperty {
+    use docx_rs::VertAlignType;
+    let mut property = docx_rs::RunProperty::new();
+    // explicit off states are written too, so they override a style that turns them on
+    match style.bold {
+        Some(true) => property = property.bold(),
+        Some(false) => property = property.disable_bold(),
+        None => {}
+    }
+    match style.italic {
+        Some(true) => property = property.italic(),
+        Some(false) => property = property.disable_italic(),
+        None => {}
+    }
+    match style.strike {
+        Some(true) => property = property.strike(),
+        Some(false) => property = property.disable_strike(),
+        None => {}
+    }
+    match style.double_strike {
+        Some(true) => property = property.dstrike(),
+        Some(false) => property = property.disable_dstrike(),
+        None => {}
+    }
+    let vert_align = match (style.superscript, style.subscript) {
+        (Some(true), _) => Some(VertAlignType::SuperScript),
+        (_, Some(true)) => Some(VertAlignType::SubScript),
+        (None, None) => None,
+        _ => Some(VertAlignType::Baseline),
+    };
+    if let Some(vert_align) = vert_align {
+        property = property.vert_align(vert_align);
+    }
+    match style.caps {
+        Some(true) => property = property.caps(),
+        Some(false) => property.caps = Some(docx_rs::Caps::new().disable()),
+        None => {}
+    }
+    if style.hidden == Some(true) {
+        property = property.vanish()
+    }
+    if let Some(underline) = style.underline {
+        property = property.underline(underline)
+    }
+    if let Some(size) = style.size {
+        property = property.size(size.max(0) as usize);
+    }
+    // \cf0 and \cb0 are written too, so they override a style's color
+    if let Some(index) = style.foreground_color {
+        property = property.color(color_value(index, color_table))
+    }
+    if let Some(index) = style.background_color {
+        let color = index
+            .checked_sub(1)
+            .and_then(|index| color_table.get(index))
+            .map(String::from)
+            .unwrap_or_else(|| "none".to_owned());
+        property = property.highlight(color)
+    }
+    property
+}
+
+/// Paragraph properties for a `ParagraphStyle`, used for both direct formatting and styles
+/// `w:spacing` of a `ParagraphStyle`
+fn line_spacing(style: &ParagraphStyle) -> Option<docx_rs::LineSpacing> {
+    use docx_rs::LineSpacingType;
+    let mut spacing = docx_rs::LineSpacing::new();
+    if let Some(before) = style.space_before {
+        spacing = spacing.before(before.max(0) as u32);
+    }
+    if let Some(after) = style.space_after {
+        spacing = spacing.after(after.max(0) as u32);
+    }
+    if let Some(before) = style.space_before_lines {
+        spacing = spacing.before_lines(before.max(0) as u32);
+    }
+    if let Some(after) = style.space_after_lines {
+        spacing = spacing.after_lines(after.max(0) as u32);
+    }
+    match style.line_spacing {
+        Some(line) if line < 0 => {
+            spacing = spacing.line_rule(LineSpacingType::Exact).line(-line);
+        }
+        Some(line) if line > 0 => {
+            let rule = if style.line_multiple == Some(true) {
+                LineSpacingType::Auto
+            } else {
+                LineSpacingType::AtLeast
+            };
+            spacing = spacing.line_rule(rule).line(line);
+        }
+        _ => {}
+    }
+    if spacing == docx_rs::LineSpacing::default() {
+        None
+    } else {
+        Some(spacing)
+    }
+}
+
+fn paragraph_property(
+    style: &ParagraphStyle,
+    color_table: &[color::Color],
+) -> docx_rs::ParagraphProperty {
+    use docx_rs::{TabLeaderType, TabValueType};
+    let mut property = docx_rs::ParagraphProperty::new();
+    if let Some(align) = style.align.as_ref() {
+        property = property.align(align.clone().into());
+    }
+    let special_indent = style
+        .first_indent
+        .map(docx_rs::SpecialIndentType::FirstLine);
+    if style.left_indent.is_some() || special_indent.is_some() || style.right_indent.is_some() {
+        property = property.indent(style.left_indent, special_indent, style.right_indent, None);
+    }
+    if let Some(spacing) = line_spacing(style) {
+        property = property.line_spacing(spacing);
+    }
+    for tab in style.tabs.iter() {
+        let align = match tab.align {
+            TabAlign::Left => TabValueType::Left,
+            TabAlign::Center => TabValueType::Center,
+            TabAlign::Right => TabValueType::Right,
+            TabAlign::Decimal => TabValueType::Decimal,
+            TabAlign::Bar => TabValueType::Bar,
+        };
+        let mut docx_tab = docx_rs::Tab::new()
+            .val(align)
+            .pos(tab.position.max(0) as usize);
+        let leader = match tab.leader {
+            TabLeader::None => None,
+            TabLeader::Dot => Some(TabLeaderType::Dot),
+            TabLeader::MiddleDot => Some(TabLeaderType::MiddleDot),
+            TabLeader::Hyphen => Some(TabLeaderType::Hyphen),
+            TabLeader::Underline => Some(TabLeaderType::Underscore),
+            // DOCX has no equal sign leader, a heavy line is the nearest
+            TabLeader::Thick | TabLeader::Equal => Some(TabLeaderType::Heavy),
+        };
+        if let Some(leader) = leader {
+            docx_tab = docx_tab.leader(leader);
+        }
+        property = property.add_tab(docx_tab);
+    }
+    if let Some(shading) = style.shading.as_ref() {
+        property = property.shading(shading_property(shading, color_table));
+    }
+    property
+}
+
+/// Hex color for table entry `index`, "auto" for 0 or a missing entry
+fn color_value(index: usize, color_table: &[color::Color]) -> String {
+    index
+        .checked_sub(1)
+        .and_then(|index| color_table.get(index))
+        .map(String::from)
+        .unwrap_or_else(|| "auto".to_owned())
+}
+
+/// What `paragraph_property` can't build for a `ParagraphStyle`: borders with art,
+/// shadows and frames, auto spacing and contextual spacing
+fn paragraph_additions(style: &ParagraphStyle, color_table: &[color::Color]) -> Vec<String> {
+    let mut additions = vec![];
+    // \sb and \sa stay in w:spacing for readers without auto spacing
+    let autospacing = [
+        ("w:beforeAutospacing", style.space_before_auto),
+        ("w:afterAutospacing", style.space_after_auto),
+    ];
+    let autospacing: String = autospacing
+        .iter()
+        .filter_map(|(attribute, auto)| {
+            auto.map(|auto| format!(r#" {}="{}""#, attribute, auto as u8))
+        })
+        .collect();
+    if !autospacing.is_empty() {
+        additions.push(format!("<w:spacing{} />", autospacing));
+    }
+    match style.contextual_spacing {
+        Some(true) => additions.push("<w:contextualSpacing />".to_owned()),
+        Some(false) => additions.push(r#"<w:contextualSpacing w:val="false" />"#.to_owned()),
+        None => {}
+    }
+    if let Some(border) = style.border.as_ref() {
+        let sides = [
+            ("top", &border.top),
+            ("left", &border.left),
+            ("bottom", &border.bottom),
+            ("right", &border.right),
+            ("between", &border.between),
+            ("bar", &border.bar),
+        ];
+        additions.extend(borders_xml("w:pBdr", &sides, color_table));
+    }
+    additions
+}
+
+fn shading_property(shading: &ParagraphShading, color_table: &[color::Color]) -> docx_rs::Shading {
+    use docx_rs::ShdType;
+    let shd_type = match shading.pattern {
+        ShadingPattern::Horizontal => ShdType::ThinHorzStripe,
+        ShadingPattern::Vertical => ShdType::ThinVertStripe,
+        ShadingPattern::ForwardDiagonal => ShdType::ThinReverseDiagStripe,
+        ShadingPattern::BackwardDiagonal => ShdType::ThinDiagStripe,
+        ShadingPattern::Cross => ShdType::ThinHorzCross,
+        ShadingPattern::DiagonalCross => ShdType::ThinDiagCross,
+        ShadingPattern::DarkHorizontal => ShdType::HorzStripe,
+        ShadingPattern::DarkVertical => ShdType::VertStripe,
+        ShadingPattern::DarkForwardDiagonal => ShdType::ReverseDiagStripe,
+        ShadingPattern::DarkBackwardDiagonal => ShdType::DiagStripe,
+        ShadingPattern::DarkCross => ShdType::HorzCross,
+        ShadingPattern::DarkDiagonalCross => ShdType::DiagCross,
+        ShadingPattern::None => {
+            // the nearest of the percentages DOCX has
+            let percents = [
+                (0i32, ShdType::Clear),
+                (500, ShdType::Pct5),
+                (1000, ShdType::Pct10),
+                (1250, ShdType::Pct12),
+                (1500, ShdType::Pct15),
+                (2000, ShdType::Pct20),
+                (2500, ShdType::Pct25),
+                (3000, ShdType::Pct30),
+                (3500, ShdType::Pct35),
+                (3750, ShdType::Pct37),
+                (4000, ShdType::Pct40),
+                (4500, ShdType::Pct45),
+                (5000, ShdType::Pct50),
+                (5500, ShdType::Pct55),
+                (6000, ShdType::Pct60),
+                (6250, ShdType::Pct62),
+                (6500, ShdType::Pct65),
+                (7000, ShdType::Pct70),
+                (7500, ShdType::Pct75),
+                (8000, ShdType::Pct80),
+                (8500, ShdType::Pct85),
+                (8750, ShdType::Pct87),
+                (9000, ShdType::Pct90),
+                (9500, ShdType::Pct95),
+                (10000, ShdType::Solid),
+            ];
+            percents
+                .iter()
+                .min_by_key(|(percent, _)| percent.abs_diff(shading.percent))
+                .map(|(_, shd_type)| *shd_type)
+                .unwrap_or(ShdType::Clear)
+        }
+    };
+    docx_rs::Shading::new()
+        .shd_type(shd_type)
+        .color(color_value(shading.foreground_color, color_table))
+        .fill(color_value(shading.background_color, color_table))
+}
+
+/// `w:rFonts` naming the font for each kind of character, `font` where none is given
+fn run_fonts(
+    fonts: &AssociatedFonts,
+    font: Option<i32>,
+    font_table: &HashMap<i32, font::Font>,
+) -> Option<docx_rs::RunFonts> {
+    let font_name = |associated: Option<i32>| {
+        associated
+            .or(font)
+            .and_then(|number| font_table.get(&number))
+            .map(|font| font.font_name.clone())
+    };
+    let names = [
+        font_name(fonts.ascii),
+        font_name(fonts.high_ansi),
+        font_name(fonts.east_asia),
+        font_name(fonts.complex),
+    ];
+    if names.iter().all(Option::is_none) {
+        return None;
+    }
+    let [ascii, high_ansi, east_asia, complex] = names;
+    let mut run_fonts = docx_rs::RunFonts::new();
+    if let Some(name) = ascii {
+        run_fonts = run_fonts.ascii(name);
+    }
+    if let Some(name) = high_ansi {
+        run_fonts = run_fonts.hi_ansi(name);
+    }
+    if let Some(name) = east_asia {
+        run_fonts = run_fonts.east_asia(name);
+    }
+    if let Some(name) = complex {
+        run_fonts = run_fonts.cs(name);
+    }
+    Some(run_fonts)
+}
+
+fn make_runs(
+    line: &Line,
+    font_table: &HashMap<i32, font::Font>,
+    style_ids: &HashMap<i32, String>,
+    color_table: &[color::Color],
+    default_font: Option<i32>,
+) -> VecDeque<docx_rs::Run> {
+    use docx_rs::Run;
+    let mut run = Run::new();
+    if let Some(style) = line.style.as_ref() {
+        run.run_property = run_property(style, color_table);
+    }
+    if let Some(id) = line.char_style.and_then(|number| style_ids.get(&number)) {
+        run = run.style(id);
+    }
+
+    let font = line.font.or(default_font);
+    if let Some(run_fonts) = run_fonts(&line.associated_fonts, font, font_table) {
+        run = run.fonts(run_fonts);
+    }
+    let texts = line.text.split('\n');
+    let mut runs = VecDeque::new();
+    for text in texts {
+        let mut text_run = run.clone();
+        for (index, piece) in text.split('\t').enumerate() {
+            if index > 0 {
+                text_run = text_run.add_tab();
+            }
+            if !piece.is_empty() || !text.contains('\t') {
+                text_run = text_run.add_text(piece);
             }
         }
-        fn make_runs(
-            line: &Line,
-            font_table: &HashMap<i32, font::Font>,
-            encoding: Option<&'static encoding_rs::Encoding>,
-            stylesheet_font_style: &FontStyle,
-            color_table: &[color::Color],
-            default_font: Option<i32>,
-        ) -> std::collections::VecDeque<Run> {
-            let mut run = Run::new();
+        runs.push_back(text_run);
+    }
+    runs
+}
 
-            let text = if let Some(font) = line.font.or(default_font) {
-                if let Some(font) = font_table.get(&font) {
-                    let text = match font.charset {
-                        Some(Charset::ShiftJIS) if encoding != Some(encoding_rs::UTF_16LE) => {
-                            crate::rtf::Text::decode_line(Some(encoding_rs::SHIFT_JIS), &line)
-                        }
-                        _ => crate::rtf::Text::decode_line(encoding, &line),
-                    };
-                    let run_font = RunFonts::new().east_asia(font.font_name.clone());
+impl Document {
+    pub fn to_docx_base64(&self) -> Result<String, Errors> {
+        let docx = self.to_docx()?;
+        Ok(base64::encode(docx))
+    }
+    pub fn to_docx(&self) -> Result<Vec<u8>, Errors> {
+        let mut cursor = std::io::Cursor::new(Vec::new());
+        self.build_xml()
+            .pack(&mut cursor)
+            .map_err(docx_rs::DocxError::from)?;
+        Ok(cursor.into_inner())
+    }
+    /// The XML parts of the docx, with a font table listing the document's fonts,
+    /// the stylesheet's `\s0` as the only Normal style, the document defaults
+    /// and the properties docx-rs has no builders for
+    pub fn build_xml(&self) -> docx_rs::XMLDocx {
+        let (docx, mut additions) = self.build_docx();
+        let mut xml = docx.build();
+        if !self.fonts.is_empty() {
+            xml.font_table = self.font_table_xml();
+        }
+        xml.document = additions.merge_document(&xml.document);
+        xml.styles = additions.merge_styles(&xml.styles);
+        xml
+    }
+    /// Ends a DOCX section other than the last with a `w:sectPr` in its last paragraph,
+    /// or in an empty paragraph when it ends with a table
+    fn end_section(
+        &self,
+        mut docx: docx_rs::Docx,
+        page_border: Option<&PageBorder>,
+        additions: &mut Additions,
+    ) -> docx_rs::Docx {
+        use docx_rs::{BuildXML, DocumentChild};
+        if !matches!(
+            docx.document.children.last(),
+            Some(DocumentChild::Paragraph(_))
+        ) {
+            docx = docx.add_paragraph(docx_rs::Paragraph::new());
+        }
+        if let Some(DocumentChild::Paragraph(p)) = docx.document.children.last_mut() {
+            let section = docx_rs::SectionProperty::new();
+            let page_borders =
+                page_border.and_then(|border| page_borders_xml(border, &self.colors));
+            if let Some(page_borders) = page_borders {
+                let section_xml = String::from_utf8_lossy(&section.build()).into_owned();
+                additions
+                    .paragraphs
+                    .entry(p.id.clone())
+                    .or_default()
+                    .property
+                    .push(merge_children(&section_xml, &[page_borders]));
+            }
+            p.property.section_property = Some(section);
+        }
+        docx
+    }
+    /// `w:docDefaults` from `\defchp`, `\defpap`, the default fonts and the default languages
+    fn set_doc_defaults(
+        &self,
+        mut docx: docx_rs::Docx,
+        additions: &mut Additions,
+    ) -> docx_rs::Docx {
+        let defaults = &self.defaults;
+        let mut font_style = defaults.font_style.clone().unwrap_or_default();
+        if let Some(size) = font_style.size.take() {
+            docx = docx.default_size(size.max(0) as usize);
+        }
+        if let Some(run_fonts) = run_fonts(&defaults.fonts(), None, &self.fonts) {
+            docx = docx.default_fonts(run_fonts);
+        }
+        let mut para_style = defaults.para_style.clone().unwrap_or_default();
+        if let Some(spacing) = line_spacing(&para_style) {
+            docx = docx.default_line_spacing(spacing);
+        }
+        para_style.space_before = None;
+        para_style.space_after = None;
+        para_style.space_before_lines = None;
+        para_style.space_after_lines = None;
+        para_style.line_spacing = None;
 
-                    run = run.fonts(run_font);
-                    // println!("{} {:?} {:?}", text, encoding, font.charset);
-                    text
+        // the rest of the defaults go in through the additions, like those of a style
+        let mut run = property_children(&run_property(&font_style, &self.colors));
+        run.extend(run_additions(&font_style, &self.colors));
+        let languages = [
+            ("w:val", defaults.language),
+            ("w:eastAsia", defaults.east_asian_language),
+            ("w:bidi", defaults.complex_language),
+        ];
+        let lang: String = languages
+            .iter()
+            .filter_map(|(attribute, lcid)| {
+                let tag = lcid.and_then(language_tag)?;
+                Some(format!(r#" {}="{}""#, attribute, tag))
+            })
+            .collect();
+        if !lang.is_empty() {
+            run.push(format!("<w:lang{} />", lang));
+        }
+        let mut paragraph = property_children(&paragraph_property(&para_style, &self.colors));
+        paragraph.extend(paragraph_additions(&para_style, &self.colors));
+        additions.defaults = StyleAdditions { run, paragraph };
+        docx
+    }
+    /// `word/fontTable.xml` with alternate names, panose, charset, family and pitch,
+    /// which Word uses to pick a substitute for fonts that aren't installed
+    fn font_table_xml(&self) -> Vec<u8> {
+        let mut fonts: Vec<_> = self.fonts.values().collect();
+        fonts.sort_by_key(|font| font.number);
+        let mut names = std::collections::HashSet::new();
+        let mut xml = String::from(
+            r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?><w:fonts xmlns:w="http://schemas.openxmlformats.org/wordprocessingml/2006/main" xmlns:r="http://schemas.openxmlformats.org/officeDocument/2006/relationships">"#,
+        );
+        for font in fonts {
+            if font.font_name.is_empty() || !names.insert(font.font_name.as_str()) {
+                continue;
+            }
+            xml.push_str(&format!(
+                r#"<w:font w:name="{}">"#,
+                escape_xml(&font.font_name)
+            ));
+            if let Some(alt) = font.alt_font_name.as_ref() {
+                xml.push_str(&format!(r#"<w:altName w:val="{}" />"#, escape_xml(alt)));
+            }
+            if let Some(panose) = font.panose.as_ref() {
+                xml.push_str(&format!(r#"<w:panose1 w:val="{}" />"#, escape_xml(panose)));
+            }
+            if let Some(charset) = font.charset.as_ref() {
+                xml.push_str(&format!(
+                    r#"<w:charset w:val="{:02X}" />"#,
+                    charset.clone() as usize
+                ));
+            }
+            let family = match font.family {
+                FontFamily::Roman => "roman",
+                FontFamily::Swiss => "swiss",
+                FontFamily::Modern => "modern",
+                FontFamily::Script => "script",
+                FontFamily::Decor => "decorative",
+                FontFamily::Nil | FontFamily::Tech | FontFamily::Bidi => "auto",
+            };
+            xml.push_str(&format!(r#"<w:family w:val="{}" />"#, family));
+            let pitch = match font.pitch {
+                Some(1) => "fixed",
+                Some(2) => "variable",
+                _ => "default",
+            };
+            xml.push_str(&format!(r#"<w:pitch w:val="{}" />"#, pitch));
+            xml.push_str("</w:font>");
+        }
+        xml.push_str("</w:fonts>");
+        xml.into_bytes()
+    }
+    /// DOCX style ids for the stylesheet entries, derived from their names
+    fn style_ids(&self) -> HashMap<i32, String> {
+        let mut stylesheets: Vec<_> = self
+            .stylesheets
+            .values()
+            .filter(|stylesheet| stylesheet.kind != StyleKind::Section)
+            .collect();
+        stylesheets.sort_by_key(|stylesheet| stylesheet.number);
+        let mut used = std::collections::HashSet::new();
+        let mut ids = HashMap::new();
+        for stylesheet in stylesheets {
+            // \s0 is the default paragraph style whatever it is called
+            let mut id: String =
+                if stylesheet.number == 0 && stylesheet.kind == StyleKind::Paragraph {
+                    NORMAL_STYLE_ID.to_owned()
                 } else {
-                    crate::rtf::Text::decode_line(encoding, &line)
-                }
+                    stylesheet
+                        .name
+                        .chars()
+                        .filter(|c| c.is_alphanumeric())
+                        .collect()
+                };
+            if id.is_empty() || used.contains(&id) {
+                id = format!("Style{}", stylesheet.number);
+            }
+            used.insert(id.clone());
+            ids.insert(stylesheet.number, id);
+        }
+        ids
+    }
+    fn add_styles(
+        &self,
+        mut docx: docx_rs::Docx,
+        style_ids: &HashMap<i32, String>,
+        additions: &mut Additions,
+    ) -> docx_rs::Docx {
+        let mut stylesheets: Vec<_> = self.stylesheets.values().collect();
+        stylesheets.sort_by_key(|stylesheet| stylesheet.number);
+        for stylesheet in stylesheets {
+            let style_type = match stylesheet.kind {
+                StyleKind::Paragraph => docx_rs::StyleType::Paragraph,
+                StyleKind::Character => docx_rs::StyleType::Character,
+                StyleKind::Table => docx_rs::StyleType::Table,
+                // section styles have no DOCX counterpart
+                StyleKind::Section => continue,
+            };
+            let id = &style_ids[&stylesheet.number];
+            let name = if stylesheet.name.is_empty() {
+                id
             } else {
-                crate::rtf::Text::decode_line(encoding, &line)
+                &stylesheet.name
             };
+            let mut style = docx_rs::Style::new(id, style_type).name(name);
+            if stylesheet.kind == StyleKind::Table {
+                // docx-rs starts table styles with single borders all round
+                style = style.table_property(docx_rs::TableProperty::without_borders());
+            }
+            if let Some(based_on) = stylesheet.based_on.and_then(|n| style_ids.get(&n)) {
+                style = style.based_on(based_on);
+            }
+            if let Some(next) = stylesheet.next.and_then(|n| style_ids.get(&n)) {
+                style = style.next(next);
+            }
+            if let Some(link) = stylesheet.link.and_then(|n| style_ids.get(&n)) {
+                style = style.link(link);
+            }
+            style = style.q_format(stylesheet.q_format);
+            if stylesheet.semi_hidden {
+                style = style.semi_hidden();
+            }
+            let mut style_additions = StyleAdditions::default();
+            if let Some(font_style) = stylesheet.font_style.as_ref() {
+                style.run_property = run_property(font_style, &self.colors);
+                style_additions.run = run_additions(font_style, &self.colors);
+            }
+            if let Some(para_style) = stylesheet.para_style.as_ref() {
+                style.paragraph_property = paragraph_property(para_style, &self.colors);
+                style_additions.paragraph = paragraph_additions(para_style, &self.colors);
+            }
+            additions.styles.insert(id.clone(), style_additions);
+            docx = docx.add_style(style);
+        }
+        docx
+    }
+    /// The docx-rs document tree, and the additions `build_xml` merges into its XML
+    fn build_docx(&self) -> (docx_rs::Docx, Additions) {
+        use docx_rs::*;
+        let mut additions = Additions::default();
+        let style_ids = self.style_ids();
+        let mut docx = self.add_styles(docx_rs::Docx::new(), &style_ids, &mut additions);
+        docx = self.set_doc_defaults(docx, &mut additions);
+        additions.replaces_normal = style_ids.values().any(|id| id == NORMAL_STYLE_ID);
+        if let Some(tab_width) = self.defaults.tab_width {
+            docx = docx.default_tab_stop(tab_width.max(0) as usize);
+        }
 
-            if let Some(style) = line.style.as_ref() {
-                if style.bold || stylesheet_font_style.bold {
-                    run = run.bold()
-                }
-                if style.italic || stylesheet_font_style.italic {
-                    run = run.italic()
+        let font_table = &self.fonts;
+        let color_table = &self.colors;
+        let default_font_number = self.default_font_number;
+        let default_para_style = style::ParagraphStyle::default();
+        let additions = RefCell::new(additions);
+        // a DOCX paragraph for each line break in `para`, with direct paragraph formatting
+        // referencing the paragraph style by id
+        let make_paragraphs = |para: &text::Paragraph| {
+            let para_style = para.style.as_ref().unwrap_or(&default_para_style);
+            let mut paragraphs = vec![];
+            let mut runs: Vec<(Run, Vec<String>)> = vec![];
+            let mut finish_paragraph = |runs: &mut Vec<(Run, Vec<String>)>| {
+                if runs.is_empty() {
+                    return;
                 }
-                /*if style.underline {
-                    run = run.underline()
-                }*/
-                if let Some(size) = style.size {
-                    run = run.size(size as usize);
-                } else if let Some(size) = stylesheet_font_style.size {
-                    run = run.size(size as usize);
+                let mut p = Paragraph::new();
+                p.property = paragraph_property(para_style, color_table);
+                if let Some(id) = para.stylesheet.and_then(|number| style_ids.get(&number)) {
+                    p = p.style(id);
                 }
-                if style.foreground_color > 0 {
-                    if let Some(color) = color_table.get(style.foreground_color - 1) {
-                        run = run.color(color)
-                    }
+                let mut paragraph_additions = ParagraphAdditions {
+                    property: paragraph_additions(para_style, color_table),
+                    ..ParagraphAdditions::default()
+                };
+                for (run, run_additions) in runs.drain(..) {
+                    p = p.add_run(run);
+                    paragraph_additions.runs.push_back(run_additions);
                 }
-                if style.background_color > 0 {
-                    if let Some(color) = color_table.get(style.background_color - 1) {
-                        run = run.highlight(color)
+                additions
+                    .borrow_mut()
+                    .add_paragraph(&p.id, paragraph_additions);
+                paragraphs.push(p);
+            };
+            for line in para.lines.iter() {
+                let run_additions = line
+                    .style
+                    .as_ref()
+                    .map(|style| run_additions(style, color_table))
+                    .unwrap_or_default();
+                let splitted = make_runs(
+                    line,
+                    font_table,
+                    &style_ids,
+                    color_table,
+                    default_font_number,
+                );
+                for (index, run) in splitted.into_iter().enumerate() {
+                    // runs after the first start a new paragraph
+                    if index > 0 {
+                        finish_paragraph(&mut runs);
                     }
+                    runs.push((run, run_additions.clone()));
                 }
             }
-            let texts = text.split('\n');
-            let mut runs = VecDeque::new();
-            for text in texts {
-                runs.push_back(run.clone().add_text(text));
-            }
-            runs
-        }
-        use docx_rs::*;
-        use std::io::{Cursor, Read, Seek};
-        let mut docx = docx_rs::Docx::new();
-        let mut cursor = Cursor::new(Vec::new());
-
-        let (text, font_table, stylesheets, color_table, default_font_number) = self.get_text();
-        let default_stylesheet = StyleSheet::default();
-        let default_font = FontStyle::new();
-        let default_para_style = style::ParagraphStyle::default();
-        if let Some(text) = text {
-            for page in text.pages {
-                //let mut i = 0;
-                for section in page.sections {
-                    for para in section.paras {
-                        let stylesheet = stylesheets
-                            .get(&para.stylesheet.unwrap_or(0))
-                            .unwrap_or(&default_stylesheet);
-                        let stylesheet_para = stylesheet
-                            .para_style
-                            .as_ref()
-                            .unwrap_or(&default_para_style);
-                        let para_style = para.style.as_ref().unwrap_or(&stylesheet_para);
-                        let align = para_style
-                            .align
-                            .as_ref()
-                            .or_else(|| stylesheet_para.align.as_ref());
-
-                        let first_indent = para_style
-                            .first_indent
-                            .as_ref()
-                            .or(stylesheet_para.first_indent.as_ref());
-                        let special_indent =
-                            first_indent.map(|indent| SpecialIndentType::FirstLine(indent.clone()));
-
-                        let stylesheet_font_style =
-                            stylesheet.font_style.as_ref().unwrap_or(&default_font);
-
-                        if let Some(table) = para.table {
+            finish_paragraph(&mut runs);
+            paragraphs
+        };
+        if let Some(text) = self.body.as_ref() {
+            // the page borders of the DOCX section being written; after a \page the
+            // section goes on in the first section of the next page
+            let mut page_border = None;
+            for page in text.pages.iter() {
+                for (index, section) in page.sections.iter().enumerate() {
+                    if index > 0 {
+                        docx =
+                            self.end_section(docx, page_border.take(), &mut additions.borrow_mut());
+                    }
+                    page_border = page_border.or(section.page_border.as_ref());
+                    for para in section.paras.iter() {
+                        if let Some(table) = para.table.as_ref() {
                             let mut rows: Vec<docx_rs::TableRow> = vec![];
                             let mut border = None;
-                            let mut grid: Vec<usize> = vec![];
-                            let mut make_grid = false;
+                            // one grid column between each pair of neighbouring boundaries
+                            let boundaries = table.cell_boundaries();
+                            let grid: Vec<usize> = boundaries
+                                .windows(2)
+                                .map(|pair| pair[1] - pair[0])
+                                .collect();
+                            let grid_index =
+                                |twips: usize| boundaries.binary_search(&twips).unwrap_or(0);
 
-                            for rtf_row in table.rows {
+                            for rtf_row in table.rows.iter() {
                                 if rtf_row.border.is_some() {
                                     border = rtf_row.border.clone();
                                 }
@@ -239,112 +1607,78 @@ impl Docx for Rtf {
                                 let mut cells: Vec<docx_rs::TableCell> = vec![];
                                 let cell_len = rtf_row.cells.len();
 
-                                for (cell_index, rtf_cell) in rtf_row.cells.into_iter().enumerate()
-                                {
+                                for (cell_index, rtf_cell) in rtf_row.cells.iter().enumerate() {
                                     if cell_index == cell_len - 1 && rtf_cell.is_empty() {
                                         continue;
                                     }
+                                    // \clmrg cells are part of the cell before them
+                                    if rtf_cell.opts.horiz_merged_cell && cell_index > 0 {
+                                        continue;
+                                    }
+                                    let merged_len = rtf_row.cells[cell_index + 1..]
+                                        .iter()
+                                        .take_while(|cell| cell.opts.horiz_merged_cell)
+                                        .count();
+                                    let merged =
+                                        &rtf_row.cells[cell_index + 1..=cell_index + merged_len];
 
-                                    let width = if let Some(left) = left {
-                                        rtf_cell.opts.right.map(|r| r.into_px() - left)
-                                    } else {
-                                        None
-                                    };
-                                    if let Some(width) = width {
-                                        if left.is_some() {
-                                            left = Some(left.unwrap() + width);
+                                    let right = merged
+                                        .last()
+                                        .unwrap_or(rtf_cell)
+                                        .opts
+                                        .right
+                                        .clone()
+                                        .map(usize::from);
+                                    let width = match (left, right) {
+                                        (Some(l), Some(r)) if r > l => {
+                                            left = Some(r);
+                                            Some((r - l, grid_index(r) - grid_index(l)))
                                         }
-                                    }
+                                        _ => None,
+                                    };
                                     let mut cell = docx_rs::TableCell::new();
-                                    if let Some(border) = rtf_cell.opts.border {
-                                        cell = cell.set_borders(border.into());
-                                    } else {
+                                    // a merged cell ends with the right border of its last cell
+                                    let mut cell_border = rtf_cell.opts.border.clone();
+                                    if let Some(last) = merged.last() {
+                                        let right = last
+                                            .opts
+                                            .border
+                                            .as_ref()
+                                            .and_then(|border| border.right.clone());
+                                        cell_border.get_or_insert_with(CellBorder::new).right =
+                                            right;
                                     }
+                                    let cell_borders = cell_border.as_ref().and_then(|border| {
+                                        table_cell_borders_xml(border, color_table)
+                                    });
+                                    additions
+                                        .borrow_mut()
+                                        .cells
+                                        .push_back(cell_borders.into_iter().collect());
 
                                     if rtf_cell.opts.vert_merge_root {
                                         cell = cell.vertical_merge(VMergeType::Restart);
                                     } else if rtf_cell.opts.vert_merged_cell {
                                         cell = cell.vertical_merge(VMergeType::Continue);
-                                    } else {
                                     }
-                                    cell = cell.vertical_align(rtf_cell.opts.vert_align.into());
-
-                                    for para in rtf_cell.paras {
-                                        let para_style =
-                                            para.style.as_ref().unwrap_or(&stylesheet_para);
-                                        let align = para_style
-                                            .align
-                                            .as_ref()
-                                            .or_else(|| stylesheet_para.align.as_ref());
-
-                                        let first_indent = para_style
-                                            .first_indent
-                                            .as_ref()
-                                            .or_else(|| stylesheet_para.first_indent.as_ref());
-                                        let special_indent = first_indent.map(|indent| {
-                                            SpecialIndentType::FirstLine(indent.clone())
-                                        });
+                                    cell = cell
+                                        .vertical_align(rtf_cell.opts.vert_align.clone().into());
 
-                                        let make_paragrah = || {
-                                            let mut p = Paragraph::new();
-
-                                            if let Some(align) = align {
-                                                p = p.align(align.clone().into());
-                                            }
-                                            p = p.indent(
-                                                para_style
-                                                    .left_indent
-                                                    .or(stylesheet_para.left_indent),
-                                                special_indent,
-                                                para_style
-                                                    .right_indent
-                                                    .or(stylesheet_para.right_indent),
-                                                None,
-                                            );
-                                            p
-                                        };
-                                        let mut runs: VecDeque<Run> = VecDeque::new();
-                                        let process_run =
-                                            |cell: TableCell, runs: &mut VecDeque<Run>| {
-                                                if !runs.is_empty() {
-                                                    let mut p = make_paragrah();
-                                                    while let Some(run) = runs.pop_front() {
-                                                        p = p.add_run(run.clone());
-                                                    }
-                                                    cell.add_paragraph(p)
-                                                } else {
-                                                    cell
-                                                }
-                                            };
-
-                                        for line in para.lines {
-                                            let mut splitted = make_runs(
-                                                &line,
-                                                &font_table,
-                                                line.encoding.or(text.encoding),
-                                                &stylesheet_font_style,
-                                                &color_table,
-                                                default_font_number,
-                                            );
-
-                                            runs.push_back(splitted.pop_front().unwrap());
-
-                                            for run in splitted {
-                                                cell = process_run(cell, &mut runs);
-                                                runs.push_back(run);
-                                            }
+                                    let paras = std::iter::once(rtf_cell)
+                                        .chain(merged.iter().filter(|cell| !cell.is_empty()))
+                                        .flat_map(|cell| cell.paras.iter());
+                                    for para in paras {
+                                        for p in make_paragraphs(para) {
+                                            cell = cell.add_paragraph(p);
                                         }
-                                        cell = process_run(cell, &mut runs);
                                     }
-                                    if let Some(width) = width {
-                                        cell = cell.width(width, WidthType::DXA);
-                                        if make_grid {
-                                            grid.push(Twips::from_px(width).into());
+                                    if let Some((width, span)) = width {
+                                        cell = cell.width(width, WidthType::Dxa);
+                                        if span > 1 {
+                                            cell = cell.grid_span(span);
                                         }
-                                    } else {
+                                    } else if right.is_none() {
                                         left = None;
-                                        grid = vec![];
-                                        make_grid = false;
                                     }
 
                                     cells.push(cell);
@@ -352,65 +1686,32 @@ impl Docx for Rtf {
                                 let row = docx_rs::TableRow::new(cells);
 
                                 rows.push(row);
-                                make_grid = false;
                             }
 
+                            let table_style = table
+                                .rows
+                                .iter()
+                                .find_map(|row| row.style)
+                                .and_then(|number| style_ids.get(&number));
                             let mut table = docx_rs::Table::new(rows);
-                            if let Some(border) = border {
-                                table = table.set_borders(border.into());
+                            if let Some(id) = table_style {
+                                table = table.style(id);
                             }
-                            if grid.len() > 0 {
+                            let table_borders = border
+                                .as_ref()
+                                .and_then(|border| table_borders_xml(border, color_table));
+                            additions
+                                .borrow_mut()
+                                .tables
+                                .push_back(table_borders.into_iter().collect());
+                            if !grid.is_empty() {
                                 table = table.set_grid(grid);
                             }
                             docx = docx.add_table(table);
                         } else {
-                            let make_paragrah = || {
-                                let mut p = Paragraph::new();
-                                if let Some(align) = align {
-                                    p = p.align(align.clone().into());
-                                }
-                                p = p.indent(
-                                    para_style.left_indent.or(stylesheet_para.left_indent),
-                                    special_indent,
-                                    para_style.right_indent.or(stylesheet_para.right_indent),
-                                    None,
-                                );
-                                p
-                            };
-                            let mut runs: VecDeque<Run> = VecDeque::new();
-                            let process_run = |docx: Docx, runs: &mut VecDeque<Run>| {
-                                if runs.len() > 0 {
-                                    let mut p = make_paragrah();
-                                    loop {
-                                        if let Some(run) = runs.pop_front() {
-                                            p = p.add_run(run.clone());
-                                        } else {
-                                            break;
-                                        }
-                                    }
-                                    docx.add_paragraph(p)
-                                } else {
-                                    docx
-                                }
-                            };
-
-                            for line in para.lines {
-                                let mut splitted = make_runs(
-                                    &line,
-                                    &font_table,
-                                    line.encoding.or(text.encoding),
-                                    &stylesheet_font_style,
-                                    &color_table,
-                                    default_font_number,
-                                );
-                                runs.push_back(splitted.pop_front().unwrap());
-
-                                for run in splitted {
-                                    docx = process_run(docx, &mut runs);
-                                    runs.push_back(run);
-                                }
+                            for p in make_paragraphs(para) {
+                                docx = docx.add_paragraph(p);
                             }
-                            docx = process_run(docx, &mut runs);
                         }
                     }
                 }
@@ -420,19 +1721,9 @@ impl Docx for Rtf {
                 p = p.add_run(run);
                 docx = docx.add_paragraph(p);
             }
+            let page_borders = page_border.and_then(|border| page_borders_xml(border, color_table));
+            additions.borrow_mut().section.extend(page_borders);
         }
-        let zip = docx.build().pack(&mut cursor);
-        if zip.is_err() {
-            return Err(Errors::DocxBuildError);
-        }
-        // TODO: add error handler
-        if let Err(_) = cursor.seek(SeekFrom::Start(0)) {
-            return Err(Errors::DocxBuildError);
-        }
-        let mut out = Vec::new();
-        if let Err(_) = cursor.read_to_end(&mut out) {
-            return Err(Errors::DocxBuildError);
-        }
-        Ok(out)
+        (docx, additions.into_inner())
     }
 }
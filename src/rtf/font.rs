@@ -11,15 +11,57 @@ pub enum FontFamily {
     Tech,
     Bidi,
 }
+/// Theme font slot a font table entry stands for (`\flomajor`, `\fdbminor`, ...)
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ThemeFont {
+    MajorAscii,
+    MajorHighAnsi,
+    MajorEastAsia,
+    MajorBidi,
+    MinorAscii,
+    MinorHighAnsi,
+    MinorEastAsia,
+    MinorBidi,
+}
+impl ThemeFont {
+    pub fn from_control_word(name: &str) -> Option<ThemeFont> {
+        let theme = match name {
+            "flomajor" => ThemeFont::MajorAscii,
+            "fhimajor" => ThemeFont::MajorHighAnsi,
+            "fdbmajor" => ThemeFont::MajorEastAsia,
+            "fbimajor" => ThemeFont::MajorBidi,
+            "flominor" => ThemeFont::MinorAscii,
+            "fhiminor" => ThemeFont::MinorHighAnsi,
+            "fdbminor" => ThemeFont::MinorEastAsia,
+            "fbiminor" => ThemeFont::MinorBidi,
+            _ => return None,
+        };
+        Some(theme)
+    }
+}
+
 #[derive(Clone, Debug)]
 pub struct Font {
     pub number: i32,
     pub family: FontFamily,
     pub font_name: String,
+    /// `{\*\falt ...}`, used when `font_name` isn't installed
     pub alt_font_name: Option<String>,
     pub charset: Option<Charset>,
+    /// `\fprqN`: 0 default, 1 fixed, 2 variable
     pub pitch: Option<i32>,
+    /// `{\*\panose ...}`, 20 hex digits
+    pub panose: Option<String>,
+    /// `\fbiasN`
+    pub bias: Option<i32>,
+    pub theme: Option<ThemeFont>,
+}
+impl Default for Font {
+    fn default() -> Self {
+        Self::new()
+    }
 }
+
 impl Font {
     pub fn new() -> Font {
         Font {
@@ -28,45 +70,478 @@ impl Font {
             alt_font_name: None,
             charset: None,
             pitch: None,
+            panose: None,
+            bias: None,
+            theme: None,
             font_name: "Times New Roman".to_owned(),
         }
     }
 }
 #[derive(Clone, Debug, PartialEq)]
 pub struct FontStyle {
-    pub bold: bool,
-    pub strike: bool,
-    pub italic: bool,
-    pub underline: bool,
-    pub foreground_color: usize,
-    pub background_color: usize,
+    pub bold: Option<bool>,
+    pub strike: Option<bool>,
+    /// `\striked1`
+    pub double_strike: Option<bool>,
+    pub italic: Option<bool>,
+    pub underline: Option<UnderlineType>,
+    /// `\ulcN`, index into the color table like `foreground_color`
+    pub underline_color: Option<usize>,
+    /// `\cfN`, index into the color table; `Some(0)` is the automatic color
+    pub foreground_color: Option<usize>,
+    pub background_color: Option<usize>,
     pub size: Option<i32>,
+    pub superscript: Option<bool>,
+    pub subscript: Option<bool>,
+    /// `\upN` (positive) or `\dnN` (negative) in half-points
+    pub offset: Option<i32>,
+    pub caps: Option<bool>,
+    pub small_caps: Option<bool>,
+    /// `\v`
+    pub hidden: Option<bool>,
+    pub outline: Option<bool>,
+    pub shadow: Option<bool>,
+    pub emboss: Option<bool>,
+    pub imprint: Option<bool>,
 }
+impl Default for FontStyle {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl FontStyle {
+    /// A style that sets nothing. Properties are `None` until set, toggles are
+    /// `Some(false)` when turned off explicitly (`\b0`, `\ulnone`, ...).
     pub fn new() -> FontStyle {
         FontStyle {
-            bold: false,
-            strike: false,
-            italic: false,
-            underline: false,
-            foreground_color: 0,
-            background_color: 0,
+            bold: None,
+            strike: None,
+            double_strike: None,
+            italic: None,
+            underline: None,
+            underline_color: None,
+            foreground_color: None,
+            background_color: None,
             size: None,
+            superscript: None,
+            subscript: None,
+            offset: None,
+            caps: None,
+            small_caps: None,
+            hidden: None,
+            outline: None,
+            shadow: None,
+            emboss: None,
+            imprint: None,
         }
     }
+    /// This style with every property it leaves unset taken from `base`
+    pub fn inherit(&self, base: &FontStyle) -> FontStyle {
+        macro_rules! pick {
+            ($($field:ident),*) => {
+                FontStyle {
+                    $($field: self.$field.or(base.$field),)*
+                }
+            };
+        }
+        pick!(
+            bold,
+            strike,
+            double_strike,
+            italic,
+            underline,
+            underline_color,
+            foreground_color,
+            background_color,
+            size,
+            superscript,
+            subscript,
+            offset,
+            caps,
+            small_caps,
+            hidden,
+            outline,
+            shadow,
+            emboss,
+            imprint
+        )
+    }
 }
 
+/// Underline of a run, from `\ul` and its variants
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum UnderlineType {
+    #[default]
+    None,
+    Single,
+    /// `\ulw`, words only
+    Words,
+    Double,
+    Thick,
+    Dotted,
+    ThickDotted,
+    Dash,
+    ThickDash,
+    LongDash,
+    ThickLongDash,
+    DotDash,
+    ThickDotDash,
+    DotDotDash,
+    ThickDotDotDash,
+    Wave,
+    HeavyWave,
+    DoubleWave,
+    Hairline,
+}
+impl UnderlineType {
+    pub fn from_control_word(name: &str) -> Option<UnderlineType> {
+        let underline = match name {
+            "ul" => UnderlineType::Single,
+            "ulw" => UnderlineType::Words,
+            "uldb" => UnderlineType::Double,
+            "ulth" => UnderlineType::Thick,
+            "uld" => UnderlineType::Dotted,
+            "ulthd" => UnderlineType::ThickDotted,
+            "uldash" => UnderlineType::Dash,
+            "ulthdash" => UnderlineType::ThickDash,
+            "ulldash" => UnderlineType::LongDash,
+            "ulthldash" => UnderlineType::ThickLongDash,
+            "uldashd" => UnderlineType::DotDash,
+            "ulthdashd" => UnderlineType::ThickDotDash,
+            "uldashdd" => UnderlineType::DotDotDash,
+            "ulthdashdd" => UnderlineType::ThickDotDotDash,
+            "ulwave" => UnderlineType::Wave,
+            "ulhwave" => UnderlineType::HeavyWave,
+            "ululdbwave" => UnderlineType::DoubleWave,
+            "ulhair" => UnderlineType::Hairline,
+            _ => return None,
+        };
+        Some(underline)
+    }
+}
+
+/// Kind of characters that `\af` picks the font for
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum FontClass {
+    /// `\loch`
+    Low,
+    /// `\hich`
+    High,
+    /// `\dbch`
+    DoubleByte,
+}
+
+/// Fonts for each kind of character in a run; `None` uses the run's `\f` font
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct AssociatedFonts {
+    /// `\loch\afN`
+    pub ascii: Option<i32>,
+    /// `\hich\afN`
+    pub high_ansi: Option<i32>,
+    /// `\dbch\afN`
+    pub east_asia: Option<i32>,
+    /// `\afN` after `\rtlch`, `\ltrch` or without any of the above
+    pub complex: Option<i32>,
+}
+
+/// Character formatting in effect; each group starts with a copy of its parent's
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct CharacterProperties {
+    /// `\fN`, `None` for the document's default font
+    pub font: Option<i32>,
+    pub associated_fonts: AssociatedFonts,
+    /// set by `\loch`, `\hich` or `\dbch`, cleared by `\rtlch` and `\ltrch`
+    pub font_class: Option<FontClass>,
+    /// `\csN` character style
+    pub char_style: Option<i32>,
+    pub style: FontStyle,
+}
+impl CharacterProperties {
+    /// Applies a character formatting control word, ignoring any other word.
+    /// Toggles like `\b` are on without a parameter and off with `0`; either way
+    /// they are set, so an explicit `\b0` overrides a bold style.
+    pub fn apply(&mut self, name: &str, value: Option<i32>) {
+        let on = value != Some(0);
+        let style = &mut self.style;
+        match name {
+            "plain" => {
+                // resets everything but the font, which goes back to \deff
+                *self = Self::default();
+            }
+            "f" => self.font = Some(value.unwrap_or(1)),
+            "cs" => self.char_style = value,
+            "loch" => self.font_class = Some(FontClass::Low),
+            "hich" => self.font_class = Some(FontClass::High),
+            "dbch" => self.font_class = Some(FontClass::DoubleByte),
+            "rtlch" | "ltrch" => self.font_class = None,
+            "af" => {
+                let fonts = &mut self.associated_fonts;
+                let slot = match self.font_class {
+                    Some(FontClass::Low) => &mut fonts.ascii,
+                    Some(FontClass::High) => &mut fonts.high_ansi,
+                    Some(FontClass::DoubleByte) => &mut fonts.east_asia,
+                    None => &mut fonts.complex,
+                };
+                *slot = value;
+            }
+            "b" => style.bold = Some(on),
+            "i" => style.italic = Some(on),
+            "ulnone" => style.underline = Some(UnderlineType::None),
+            "ulc" => style.underline_color = Some(value.unwrap_or(0).max(0) as usize),
+            "strike" => style.strike = Some(on),
+            "striked" => style.double_strike = Some(on),
+            "super" => {
+                style.superscript = Some(on);
+                style.subscript = Some(false);
+            }
+            "sub" => {
+                style.subscript = Some(on);
+                style.superscript = Some(false);
+            }
+            "nosupersub" => {
+                style.superscript = Some(false);
+                style.subscript = Some(false);
+            }
+            "up" => style.offset = Some(value.unwrap_or(6)),
+            "dn" => style.offset = Some(-value.unwrap_or(6)),
+            "caps" => style.caps = Some(on),
+            "scaps" => style.small_caps = Some(on),
+            "v" => style.hidden = Some(on),
+            "outl" => style.outline = Some(on),
+            "shad" => style.shadow = Some(on),
+            "embo" => style.emboss = Some(on),
+            "impr" => style.imprint = Some(on),
+            // a negative size is invalid, the size in effect stays
+            "fs" if value.is_some_and(|size| size < 0) => {}
+            "fs" => style.size = value,
+            "cf" => style.foreground_color = Some(value.unwrap_or(0).max(0) as usize),
+            "cb" => style.background_color = Some(value.unwrap_or(0).max(0) as usize),
+            _ => {
+                if let Some(underline) = UnderlineType::from_control_word(name) {
+                    style.underline = Some(if on { underline } else { UnderlineType::None });
+                }
+            }
+        }
+    }
+    /// The font text written now is in: the associated font picked by
+    /// `\loch`, `\hich` or `\dbch` when there is one, else `\f`
+    pub fn text_font(&self) -> Option<i32> {
+        let fonts = &self.associated_fonts;
+        let associated = match self.font_class {
+            Some(FontClass::Low) => fonts.ascii,
+            Some(FontClass::High) => fonts.high_ansi,
+            Some(FontClass::DoubleByte) => fonts.east_asia,
+            None => None,
+        };
+        associated.or(self.font)
+    }
+    /// The style of text written now, `None` when nothing is set
+    pub fn font_style(&self) -> Option<FontStyle> {
+        if self.style == FontStyle::default() {
+            None
+        } else {
+            Some(self.style.clone())
+        }
+    }
+}
+
+/// ANSI code page for a language id (`\langN`, `\deflangN`, `\deflangfeN`)
+pub fn language_codepage(lcid: i32) -> u16 {
+    match lcid & 0x3ff {
+        0x11 => 932,
+        0x12 => 949,
+        // Chinese: Taiwan, Hong Kong and Macao use traditional characters
+        0x04 if [0x0404, 0x0c04, 0x1404].contains(&lcid) => 950,
+        0x04 => 936,
+        0x19 | 0x22 | 0x23 | 0x02 | 0x2f | 0x3f | 0x40 | 0x44 | 0x50 => 1251,
+        // Serbian and Bosnian in Cyrillic script
+        0x1a if [0x0c1a, 0x1c1a, 0x201a].contains(&lcid) => 1251,
+        0x05 | 0x0e | 0x15 | 0x18 | 0x1a | 0x1b | 0x1c | 0x24 => 1250,
+        0x08 => 1253,
+        0x1f | 0x2c | 0x43 => 1254,
+        0x0d => 1255,
+        0x01 | 0x20 | 0x29 => 1256,
+        0x25..=0x27 => 1257,
+        0x2a => 1258,
+        0x1e => 874,
+        _ => 1252,
+    }
+}
+
+/// Language tag for a language id, as written in DOCX `w:lang`
+pub fn language_tag(lcid: i32) -> Option<&'static str> {
+    let tag = match lcid {
+        0x0401 => "ar-SA",
+        0x0402 => "bg-BG",
+        0x0404 => "zh-TW",
+        0x0405 => "cs-CZ",
+        0x0406 => "da-DK",
+        0x0407 => "de-DE",
+        0x0408 => "el-GR",
+        0x0409 => "en-US",
+        0x040b => "fi-FI",
+        0x040c => "fr-FR",
+        0x040d => "he-IL",
+        0x040e => "hu-HU",
+        0x0410 => "it-IT",
+        0x0411 => "ja-JP",
+        0x0412 => "ko-KR",
+        0x0413 => "nl-NL",
+        0x0414 => "nb-NO",
+        0x0415 => "pl-PL",
+        0x0416 => "pt-BR",
+        0x0419 => "ru-RU",
+        0x041d => "sv-SE",
+        0x041e => "th-TH",
+        0x041f => "tr-TR",
+        0x0422 => "uk-UA",
+        0x042a => "vi-VN",
+        0x0804 => "zh-CN",
+        0x0809 => "en-GB",
+        0x0816 => "pt-PT",
+        0x0c04 => "zh-HK",
+        0x0c0a => "es-ES",
+        _ => return None,
+    };
+    Some(tag)
+}
+
+/// Character set from `\fcharsetN`
 #[derive(Clone, Debug, PartialEq)]
 pub enum Charset {
     Ansi = 0,
+    /// use the document's code page
+    Default = 1,
+    Symbol = 2,
+    Mac = 77,
+    MacShiftJIS = 78,
+    MacHangul = 79,
+    MacGB2312 = 80,
+    MacBig5 = 81,
+    MacJohab = 82,
+    MacHebrew = 83,
+    MacArabic = 84,
+    MacGreek = 85,
+    MacTurkish = 86,
+    MacThai = 87,
+    MacEastEurope = 88,
+    MacCyrillic = 89,
     ShiftJIS = 128,
+    Hangul = 129,
+    Johab = 130,
+    GB2312 = 134,
+    Big5 = 136,
+    Greek = 161,
+    Turkish = 162,
+    Vietnamese = 163,
+    Hebrew = 177,
+    Arabic = 178,
+    ArabicTraditional = 179,
+    ArabicUser = 180,
+    HebrewUser = 181,
+    Baltic = 186,
+    Cyrillic = 204,
+    Thai = 222,
+    EastEurope = 238,
+    PC437 = 254,
+    OEM = 255,
 }
 impl From<usize> for Charset {
     fn from(num: usize) -> Charset {
         match num {
             0 => Charset::Ansi,
+            2 => Charset::Symbol,
+            77 => Charset::Mac,
+            78 => Charset::MacShiftJIS,
+            79 => Charset::MacHangul,
+            80 => Charset::MacGB2312,
+            81 => Charset::MacBig5,
+            82 => Charset::MacJohab,
+            83 => Charset::MacHebrew,
+            84 => Charset::MacArabic,
+            85 => Charset::MacGreek,
+            86 => Charset::MacTurkish,
+            87 => Charset::MacThai,
+            88 => Charset::MacEastEurope,
+            89 => Charset::MacCyrillic,
             128 => Charset::ShiftJIS,
-            _ => Charset::Ansi,
+            129 => Charset::Hangul,
+            130 => Charset::Johab,
+            134 => Charset::GB2312,
+            136 => Charset::Big5,
+            161 => Charset::Greek,
+            162 => Charset::Turkish,
+            163 => Charset::Vietnamese,
+            177 => Charset::Hebrew,
+            178 => Charset::Arabic,
+            179 => Charset::ArabicTraditional,
+            180 => Charset::ArabicUser,
+            181 => Charset::HebrewUser,
+            186 => Charset::Baltic,
+            204 => Charset::Cyrillic,
+            222 => Charset::Thai,
+            238 => Charset::EastEurope,
+            254 => Charset::PC437,
+            255 => Charset::OEM,
+            _ => Charset::Default,
+        }
+    }
+}
+impl Charset {
+    /// Windows code page of the charset, `None` for `Default`, `Symbol` and Mac Johab
+    pub fn codepage(&self) -> Option<u16> {
+        let cp = match self {
+            Charset::Ansi => 1252,
+            Charset::Default | Charset::Symbol | Charset::MacJohab => return None,
+            Charset::Mac => 10000,
+            Charset::MacShiftJIS => 10001,
+            Charset::MacHangul => 10003,
+            Charset::MacGB2312 => 10008,
+            Charset::MacBig5 => 10002,
+            Charset::MacHebrew => 10005,
+            Charset::MacArabic => 10004,
+            Charset::MacGreek => 10006,
+            Charset::MacTurkish => 10081,
+            Charset::MacThai => 10021,
+            Charset::MacEastEurope => 10029,
+            Charset::MacCyrillic => 10007,
+            Charset::ShiftJIS => 932,
+            Charset::Hangul => 949,
+            Charset::Johab => 1361,
+            Charset::GB2312 => 936,
+            Charset::Big5 => 950,
+            Charset::Greek => 1253,
+            Charset::Turkish => 1254,
+            Charset::Vietnamese => 1258,
+            Charset::Hebrew => 1255,
+            Charset::Arabic => 1256,
+            Charset::ArabicTraditional => 708,
+            Charset::ArabicUser => 720,
+            Charset::HebrewUser => 862,
+            Charset::Baltic => 1257,
+            Charset::Cyrillic => 1251,
+            Charset::Thai => 874,
+            Charset::EastEurope => 1250,
+            Charset::PC437 => 437,
+            Charset::OEM => 437,
+        };
+        Some(cp)
+    }
+    /// Decoder for text in this charset. `None` when it defers to the document's
+    /// code page, or when encoding_rs has no such encoding (Johab, DOS and most Mac
+    /// code pages).
+    pub fn encoding(&self) -> Option<&'static encoding_rs::Encoding> {
+        use encoding_rs::*;
+        match self {
+            // the Mac CJK code pages are close enough to their Windows counterparts
+            Charset::MacShiftJIS => Some(SHIFT_JIS),
+            Charset::MacHangul => Some(EUC_KR),
+            Charset::MacGB2312 => Some(GBK),
+            Charset::MacBig5 => Some(BIG5),
+            Charset::MacCyrillic => Some(X_MAC_CYRILLIC),
+            Charset::ArabicTraditional => Some(ISO_8859_6),
+            _ => self.codepage().and_then(codepage::to_encoding),
         }
     }
 }
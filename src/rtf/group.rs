@@ -2,59 +2,129 @@ use super::*;
 #[derive(Clone)]
 pub struct GroupState {
     pub destinations: Rc<RefCell<HashMap<String, Destination>>>,
+    pub reporter: Rc<RefCell<Reporter>>,
+    /// font table, to decode text in the charset of the current font
+    pub fonts: Rc<RefCell<HashMap<i32, Font>>>,
+    /// caller-supplied encoding for documents that declare neither code page nor language
+    pub default_encoding: Option<&'static encoding_rs::Encoding>,
+    /// keep the data of `BINARY_DESTINATIONS` instead of only counting it
+    pub keep_binary_destinations: bool,
     pub cur_destination: Option<String>,
+    /// whether the group writes decoded text, as opposed to raw bytes, to its destination
+    pub text_destination: bool,
     pub dest_encoding: Option<&'static encoding_rs::Encoding>,
     pub values: HashMap<String, Option<i32>>,
     pub opt_ignore_next_control: bool,
-    pub cur_font: Option<i32>,
+    pub char_props: CharacterProperties,
+    /// text and `\'xx` bytes not decoded yet
     pub buffer: Vec<u8>,
+    /// where the bytes in `buffer` started
+    pub buffer_location: Location,
     pub border_select: BorderSelect,
-    pub unicode_count: usize,
+    /// fallback characters still to skip after `\uN`
     pub ignore_count: usize,
-    pub color_index: usize,
+    /// number of fallback characters after each `\uN`, set by `\ucN`
+    pub unicode_skip: usize,
+    /// first half of a surrogate pair, waiting for the second `\uN`
+    pub high_surrogate: Option<u16>,
+    /// tab stops of the paragraph so far
+    pub tab_stops: Vec<TabStop>,
+    /// alignment and leader for the next `\txN`
+    pub next_tab: TabStop,
+    pub para_border: Option<ParagraphBorder>,
+    pub para_shading: Option<ParagraphShading>,
     pub colors: std::collections::VecDeque<Color>,
 }
 impl GroupState {
-    pub fn new(destinations: Rc<RefCell<HashMap<String, Destination>>>) -> Self {
+    /// destinations holding picture and object data, which can be large
+    pub const BINARY_DESTINATIONS: [&'static str; 2] = ["pict", "objdata"];
+
+    pub fn new(
+        destinations: Rc<RefCell<HashMap<String, Destination>>>,
+        reporter: Rc<RefCell<Reporter>>,
+        fonts: Rc<RefCell<HashMap<i32, Font>>>,
+        default_encoding: Option<&'static encoding_rs::Encoding>,
+        keep_binary_destinations: bool,
+    ) -> Self {
         Self {
             destinations,
+            reporter,
+            fonts,
+            default_encoding,
+            keep_binary_destinations,
             cur_destination: None,
+            text_destination: false,
             dest_encoding: None,
             values: HashMap::new(),
             opt_ignore_next_control: false,
-            cur_font: None,
+            char_props: CharacterProperties::default(),
             buffer: vec![],
+            buffer_location: Location::default(),
             border_select: BorderSelect::Paragraph,
-            unicode_count: 0,
             ignore_count: 0,
-            color_index: 0,
+            unicode_skip: 1,
+            high_surrogate: None,
+            tab_stops: vec![],
+            next_tab: TabStop::default(),
+            para_border: None,
+            para_shading: None,
             colors: std::collections::VecDeque::new(),
         }
     }
 
+    pub fn report(&self, kind: WarningKind) {
+        self.reporter.borrow_mut().report(kind);
+    }
+
     pub fn set_codepage(&mut self, cp: u16) {
-        self.dest_encoding = codepage::to_encoding(cp);
+        match codepage::to_encoding(cp) {
+            Some(encoding) => self.dest_encoding = Some(encoding),
+            None => {
+                // keep whatever was in effect before
+                let used = self.dest_encoding.unwrap_or(encoding_rs::WINDOWS_1252);
+                self.report(WarningKind::EncodingFallback {
+                    codepage: Some(cp),
+                    used: used.name(),
+                });
+                self.dest_encoding = Some(used);
+            }
+        }
     }
 
     pub fn get_encoding(&self) -> Option<&'static encoding_rs::Encoding> {
         self.dest_encoding
     }
 
-    pub fn set_encoding(&mut self, encoding: Option<&'static encoding_rs::Encoding>) {
-        self.dest_encoding = encoding;
+    /// Encoding implied by `\deflangfe` or `\deflang`
+    pub fn language_encoding(&self) -> Option<&'static encoding_rs::Encoding> {
+        let lang = self
+            .values
+            .get("deflangfe")
+            .or_else(|| self.values.get("deflang"))
+            .cloned()
+            .flatten()?;
+        codepage::to_encoding(language_codepage(lang))
+    }
+
+    /// Encoding for text when no code page is in effect
+    pub fn fallback_encoding(&self) -> &'static encoding_rs::Encoding {
+        self.language_encoding()
+            .or(self.default_encoding)
+            .unwrap_or(encoding_rs::WINDOWS_1252)
     }
 
     pub fn set_destination(&mut self, name: &str, uses_encoding: bool) {
         self.cur_destination = Some(name.to_owned());
+        self.text_destination = uses_encoding;
         let mut dest = (*self.destinations).borrow_mut();
         match dest.get(name) {
+            // content of the other kind is dropped and reported when it's written
             Some(Destination::Text(text)) => {
                 debug!(
                     "Switching to destination {}, with current page length {})",
                     name,
                     text.pages.len()
                 );
-                assert!(uses_encoding);
             }
             Some(Destination::Bytes(bytes)) => {
                 debug!(
@@ -62,17 +132,18 @@ impl GroupState {
                     name,
                     bytes.len()
                 );
-                assert!(!uses_encoding);
+            }
+            Some(Destination::Skipped(_)) => {
+                debug!("Switching to skipped destination {}", name);
             }
             None => {
-                if uses_encoding {
-                    dest.insert(
-                        name.to_string(),
-                        Destination::Text(Text {
-                            pages: vec![Page::new()],
-                            encoding: self.get_encoding(),
-                        }),
-                    );
+                if !uses_encoding
+                    && !self.keep_binary_destinations
+                    && Self::BINARY_DESTINATIONS.contains(&name)
+                {
+                    dest.insert(name.to_string(), Destination::Skipped(0));
+                } else if uses_encoding {
+                    dest.insert(name.to_string(), Destination::Text(Text::new()));
                 } else {
                     dest.insert(name.to_string(), Destination::Bytes(Vec::new()));
                 }
@@ -83,25 +154,12 @@ impl GroupState {
     pub fn get_destination_name(&self) -> Option<String> {
         self.cur_destination.clone()
     }
-    pub fn new_line(&mut self) {
-        self.flush();
-        let dest_name = match self.get_destination_name() {
-            Some(name) => name.clone(),
-            None => {
-                warn!("Document format error: Document text found outside of any document group",);
-                return;
-            }
-        };
-        if let Some(dest) = (*self.destinations).borrow_mut().get_mut(&dest_name) {
-            dest.new_line();
-        }
-    }
     pub fn new_section(&mut self) {
         self.flush();
         let dest_name = match self.get_destination_name() {
             Some(name) => name.clone(),
             None => {
-                warn!("Document format error: Document text found outside of any document group",);
+                self.report(WarningKind::ContentOutsideDocument);
                 return;
             }
         };
@@ -114,7 +172,7 @@ impl GroupState {
         let dest_name = match self.get_destination_name() {
             Some(name) => name.clone(),
             None => {
-                warn!("Document format error: Document text found outside of any document group",);
+                self.report(WarningKind::ContentOutsideDocument);
                 return;
             }
         };
@@ -127,7 +185,7 @@ impl GroupState {
         let dest_name = match self.get_destination_name() {
             Some(name) => name.clone(),
             None => {
-                warn!("Document format error: Document text found outside of any document group",);
+                self.report(WarningKind::ContentOutsideDocument);
                 return;
             }
         };
@@ -136,38 +194,10 @@ impl GroupState {
         }
     }
     pub fn get_cur_style(&self) -> Option<FontStyle> {
-        let bold = self.has_key("b");
-        let italic = self.has_key("i");
-        let underline = self.has_key("ul");
-        let size = self.values.get("fs").unwrap_or(&None).clone();
-        let cb: usize = self
-            .values
-            .get("cb")
-            .unwrap_or(&Some(0))
-            .unwrap_or(0)
-            .clone() as usize;
-        let cf: usize = self
-            .values
-            .get("cf")
-            .unwrap_or(&Some(0))
-            .unwrap_or(0)
-            .clone() as usize;
-
-        if !bold && !italic && !underline && size.is_none() && cf == 0 && cb == 0 {
-            return None;
-        }
-        Some(FontStyle {
-            bold,
-            italic,
-            strike: false,
-            underline,
-            size,
-            foreground_color: cf,
-            background_color: cb,
-        })
+        self.char_props.font_style()
     }
     pub fn get_cur_stylesheet(&self) -> Option<i32> {
-        let stylesheet_num = self.values.get("s").unwrap_or(&None).clone();
+        let stylesheet_num = *self.values.get("s").unwrap_or(&None);
         stylesheet_num
     }
     pub fn reset_paragraph_properies(&mut self) {
@@ -178,11 +208,28 @@ impl GroupState {
         self.values.remove("fi");
         self.values.remove("li");
         self.values.remove("ri");
+        for name in [
+            "sb",
+            "sa",
+            "sbauto",
+            "saauto",
+            "lisb",
+            "lisa",
+            "sl",
+            "slmult",
+            "contextualspace",
+        ]
+        .iter()
+        {
+            self.values.remove(*name);
+        }
         self.values.remove("intbl");
-        self.values.remove("b");
-        self.values.remove("ul");
-        self.values.remove("i");
-        self.values.remove("fs");
+        self.values.remove("s");
+        self.tab_stops.clear();
+        self.next_tab = TabStop::default();
+        self.para_border = None;
+        self.para_shading = None;
+        self.border_select = BorderSelect::Paragraph;
     }
     pub fn get_cur_para_style(&self) -> Option<ParagraphStyle> {
         let align = if self.has_key("ql") {
@@ -196,23 +243,32 @@ impl GroupState {
         } else {
             None
         };
-        let first_indent = self.values.get("fi").unwrap_or(&None).clone();
-        let left_indent = self.values.get("li").unwrap_or(&None).clone();
-        let right_indent = self.values.get("ri").unwrap_or(&None).clone();
-        if align.is_none()
-            && first_indent.is_none()
-            && left_indent.is_none()
-            && right_indent.is_none()
-        {
-            return None;
-        }
-        //self.destinations.get_mut("stylesheet");
-        Some(ParagraphStyle {
+        let value = |name: &str| *self.values.get(name).unwrap_or(&None);
+        // toggles without a parameter are on
+        let toggle = |name: &str| self.values.get(name).map(|value| value.unwrap_or(1) != 0);
+        let style = ParagraphStyle {
             align,
-            first_indent,
-            left_indent,
-            right_indent,
-        })
+            first_indent: value("fi"),
+            left_indent: value("li"),
+            right_indent: value("ri"),
+            space_before: value("sb"),
+            space_after: value("sa"),
+            space_before_auto: toggle("sbauto"),
+            space_after_auto: toggle("saauto"),
+            space_before_lines: value("lisb"),
+            space_after_lines: value("lisa"),
+            line_spacing: value("sl"),
+            line_multiple: toggle("slmult"),
+            contextual_spacing: toggle("contextualspace"),
+            tabs: self.tab_stops.clone(),
+            border: self.para_border.clone(),
+            shading: self.para_shading.clone(),
+        };
+        if style == ParagraphStyle::default() {
+            None
+        } else {
+            Some(style)
+        }
     }
     pub fn next_color_index(&mut self) {
         self.colors.push_back(Color::default());
@@ -220,103 +276,157 @@ impl GroupState {
     pub fn shift_color(&mut self) -> Option<Color> {
         self.colors.pop_front()
     }
+    /// Decodes and writes the buffered bytes, reporting problems where they started
     pub fn flush(&mut self) {
-        if self.buffer.len() > 0 {
-            {
-                self.write(&self.buffer.clone());
-            }
-            self.buffer.clear();
+        if !self.buffer.is_empty() {
+            let buffer = std::mem::take(&mut self.buffer);
+            let location = self.buffer_location.clone();
+            let current = std::mem::replace(&mut self.reporter.borrow_mut().location, location);
+            self.write(&buffer);
+            self.reporter.borrow_mut().location = current;
         }
     }
+    /// Holds text and `\'xx` bytes until they can be decoded together; byte destinations,
+    /// like a `\pict` body, take them as they come
     pub fn buffer(&mut self, bytes: &[u8]) {
+        if !self.text_destination {
+            self.flush();
+            self.write(bytes);
+            return;
+        }
+        if self.buffer.is_empty() {
+            self.buffer_location = self.reporter.borrow().location.clone();
+        }
         self.buffer.extend(bytes);
     }
+    /// Writes the UTF-16 code unit of `\uN`; values above 32767 come as negative numbers
     pub fn write_unicode(&mut self, value: i32) {
         self.flush();
+        let unit = (value & 0xFFFF) as u16;
+        match unit {
+            0xD800..=0xDBFF => {
+                self.flush_surrogate();
+                self.high_surrogate = Some(unit);
+            }
+            0xDC00..=0xDFFF => match self.high_surrogate.take() {
+                Some(high) => self.write_text(&String::from_utf16_lossy(&[high, unit])),
+                None => self.write_text("\u{fffd}"),
+            },
+            _ => self.write_text(&String::from_utf16_lossy(&[unit])),
+        }
+    }
+    /// Uses up one fallback character of the last `\uN`; true when the caller must drop it.
+    /// Buffered `\'xx` bytes count one each, so they are flushed first.
+    pub fn skip_fallback(&mut self) -> bool {
+        self.flush();
+        if self.ignore_count > 0 {
+            self.ignore_count -= 1;
+            true
+        } else {
+            false
+        }
+    }
+    /// Writes a high surrogate that was not followed by a low one as U+FFFD
+    pub fn flush_surrogate(&mut self) {
+        if self.high_surrogate.take().is_some() {
+            self.write_text("\u{fffd}");
+        }
+    }
+    /// Appends already decoded text to the current text destination
+    pub fn write_text(&mut self, text: &str) {
+        self.flush_surrogate();
         let dest_name = match self.get_destination_name() {
             Some(name) => name.clone(),
             None => {
-                warn!(
-                    "Document format error: Document text found outside of any document group: '{:?}'",
-                    value
-                );
+                self.report(WarningKind::ContentOutsideDocument);
                 return;
             }
         };
-        if let Some(dest) = (*self.destinations).borrow_mut().get_mut(&dest_name) {
-            match dest {
-                Destination::Text(_) => {
-                    dest.append_text(
-                        &vec![(value & 0xff) as u8, (value >> 8) as u8],
-                        self.cur_font,
-                        self.get_cur_style(),
-                        self.get_cur_para_style(),
-                        self.get_cur_stylesheet(),
-                        self.has_key("intbl"),
-                        encoding_rs::UTF_16LE,
-                    );
-                }
-                Destination::Bytes(_) => { /* NOP */ }
-            }
+        let stored = match (*self.destinations).borrow_mut().get_mut(&dest_name) {
+            Some(dest) if self.text_destination => dest.append_text(
+                text,
+                &self.char_props,
+                self.get_cur_para_style(),
+                self.get_cur_stylesheet(),
+                self.has_key("intbl"),
+            ),
+            // \uN in a byte destination is kept as UTF-8
+            Some(dest) => dest.append_bytes(text.as_bytes()),
+            None => text.is_empty(),
+        };
+        if !stored {
+            self.report(WarningKind::DroppedText(text.len()));
+        }
+    }
+    /// Encoding of text written now: the charset of the font being defined or used,
+    /// then the code page in effect
+    pub fn text_encoding(&self) -> &'static encoding_rs::Encoding {
+        let charset = match self.values.get("fcharset") {
+            // inside the font table
+            Some(Some(charset)) => Some(Charset::from(*charset as usize)),
+            _ => self
+                .char_props
+                .text_font()
+                .or_else(|| self.values.get("deff").cloned().flatten())
+                .and_then(|number| {
+                    let fonts = self.fonts.borrow();
+                    fonts.get(&number).and_then(|font| font.charset.clone())
+                }),
+        };
+        if let Some(encoding) = charset.as_ref().and_then(Charset::encoding) {
+            return encoding;
         }
+        self.dest_encoding.unwrap_or_else(|| {
+            // no \ansi, \mac, \pc or \ansicpg
+            let used = self.fallback_encoding();
+            if self.language_encoding().is_none() {
+                self.report(WarningKind::EncodingFallback {
+                    codepage: None,
+                    used: used.name(),
+                });
+            }
+            used
+        })
     }
     pub fn write(&mut self, bytes: &[u8]) {
         let dest_name = match self.get_destination_name() {
             Some(name) => name.clone(),
             None => {
-                warn!(
-                    "Document format error: Document text found outside of any document group: '{:?}'",
-                    bytes
-                );
+                self.report(WarningKind::ContentOutsideDocument);
                 return;
             }
         };
         if dest_name == "colortbl" {
-            if bytes.len() == 1 && bytes.get(0) == Some(&59 /* = ';' */) {
+            if bytes.len() == 1 && bytes.first() == Some(&59 /* = ';' */) {
                 self.next_color_index();
             }
+            return;
+        }
+        if !(*self.destinations).borrow().contains_key(&dest_name) {
+            self.report(WarningKind::DroppedText(bytes.len()));
+            return;
+        }
+        let b = if bytes.len() > self.ignore_count {
+            let ic = self.ignore_count;
+            if ic > 0 {
+                self.ignore_count = 0;
+            }
+            &bytes[ic..]
+        } else if bytes.is_empty() {
+            &[]
+        } else {
+            self.ignore_count -= bytes.len();
+            &[]
+        };
+        if self.text_destination {
+            if !b.is_empty() {
+                let text = self.text_encoding().decode_without_bom_handling(b).0;
+                self.write_text(&text);
+            }
         } else if let Some(dest) = (*self.destinations).borrow_mut().get_mut(&dest_name) {
-            let b = if bytes.len() > self.ignore_count {
-                let ic = self.ignore_count;
-                if ic > 0 {
-                    self.ignore_count = 0;
-                }
-                &bytes[ic..]
-            } else if bytes.is_empty() {
-                &[]
-            } else {
-                self.ignore_count -= bytes.len();
-                &[]
-            };
-
-            match dest {
-                Destination::Text(_) => {
-                    if let Some(decoder) = self.dest_encoding {
-                        dest.append_text(
-                            b,
-                            self.cur_font,
-                            self.get_cur_style(),
-                            self.get_cur_para_style(),
-                            self.get_cur_stylesheet(),
-                            self.has_key("intbl"),
-                            decoder,
-                        );
-                    } else {
-                        warn!(
-                            "Writing to a text destination ({}) with no encoding set!",
-                            dest_name
-                        );
-                    }
-                }
-                Destination::Bytes(_) => {
-                    dest.append_bytes(b);
-                }
+            if !dest.append_bytes(b) {
+                self.report(WarningKind::DroppedText(b.len()));
             }
-        } else {
-            panic!(
-                "Programming error: specified destination {} doesn't exist after verifying its existence",
-                dest_name
-            );
         }
     }
 
@@ -334,7 +444,7 @@ impl GroupState {
         let dest_name = match self.get_destination_name() {
             Some(name) => name.clone(),
             None => {
-                warn!("Document format error: Document text found outside of any document group",);
+                self.report(WarningKind::ContentOutsideDocument);
                 return;
             }
         };
@@ -354,7 +464,7 @@ impl GroupState {
         let dest_name = match self.get_destination_name() {
             Some(name) => name.clone(),
             None => {
-                warn!("Document format error: Document text found outside of any document group",);
+                self.report(WarningKind::ContentOutsideDocument);
                 return;
             }
         };
@@ -365,7 +475,7 @@ impl GroupState {
             if
             // let last_paragraph = text.last_paragraph();
             last_paragraph.table.is_none()
-                && (last_paragraph.lines.len() > 1 || !last_paragraph.lines[0].bytes.is_empty())
+                && (last_paragraph.lines.len() > 1 || !last_paragraph.is_empty())
             {
                 let mut p = Paragraph::new();
                 p.table = Some(Table::new());
@@ -388,50 +498,90 @@ impl GroupState {
             }
         }
     }
-    pub fn set_border_type(&mut self, border_type: BorderType) {
+    /// Applies `update` to the selected border: a side of the paragraph, which belongs
+    /// to the group's paragraph properties, or a side of the current row or cell
+    pub fn update_border(&mut self, update: impl Fn(&mut Border)) {
+        if let BorderSelect::ParagraphTop
+        | BorderSelect::ParagraphLeft
+        | BorderSelect::ParagraphBottom
+        | BorderSelect::ParagraphRight
+        | BorderSelect::ParagraphBetween
+        | BorderSelect::ParagraphBar
+        | BorderSelect::ParagraphBox = self.border_select
+        {
+            let border = self
+                .para_border
+                .get_or_insert_with(ParagraphBorder::default);
+            for side in border.sides_mut(&self.border_select) {
+                update(side);
+            }
+            return;
+        }
         let dest_name = match self.get_destination_name() {
             Some(name) => name.clone(),
             None => {
-                warn!("Document format error: Document text found outside of any document group",);
+                self.report(WarningKind::ContentOutsideDocument);
                 return;
             }
         };
         if let Some(Destination::Text(text)) = (*self.destinations).borrow_mut().get_mut(&dest_name)
         {
-            text.set_border_type(self.border_select.clone(), border_type);
+            if let Some(border) = text.get_border(self.border_select.clone()) {
+                update(border);
+            }
+        }
+    }
+    /// Starts a new definition of the paragraph borders `select` refers to
+    pub fn select_paragraph_border(&mut self, select: BorderSelect) {
+        let border = self
+            .para_border
+            .get_or_insert_with(ParagraphBorder::default);
+        for side in border.sides_mut(&select) {
+            *side = Border::new();
         }
+        self.border_select = select;
+    }
+    pub fn set_border_type(&mut self, border_type: BorderType) {
+        self.update_border(|border| border.border_type = border_type.clone());
     }
     pub fn set_border_width(&mut self, border_width: usize) {
+        self.update_border(|border| border.width = border_width);
+    }
+    fn shading_mut(&mut self) -> &mut ParagraphShading {
+        self.para_shading
+            .get_or_insert_with(ParagraphShading::default)
+    }
+    pub fn set_row_last(&mut self) {
         let dest_name = match self.get_destination_name() {
             Some(name) => name.clone(),
             None => {
-                warn!("Document format error: Document text found outside of any document group",);
+                self.report(WarningKind::ContentOutsideDocument);
                 return;
             }
         };
         if let Some(Destination::Text(text)) = (*self.destinations).borrow_mut().get_mut(&dest_name)
         {
-            text.set_border_width(self.border_select.clone(), border_width);
+            text.set_row_last();
         }
     }
-    pub fn set_row_last(&mut self) {
+    pub fn set_table_style(&mut self, number: i32) {
         let dest_name = match self.get_destination_name() {
             Some(name) => name.clone(),
             None => {
-                warn!("Document format error: Document text found outside of any document group",);
+                self.report(WarningKind::ContentOutsideDocument);
                 return;
             }
         };
         if let Some(Destination::Text(text)) = (*self.destinations).borrow_mut().get_mut(&dest_name)
         {
-            text.set_row_last();
+            text.set_table_style(number);
         }
     }
     pub fn fit_text(&mut self, twips: i32) {
         let dest_name = match self.get_destination_name() {
             Some(name) => name.clone(),
             None => {
-                warn!("Document format error: Document text found outside of any document group",);
+                self.report(WarningKind::ContentOutsideDocument);
                 return;
             }
         };
@@ -444,7 +594,7 @@ impl GroupState {
         let dest_name = match self.get_destination_name() {
             Some(name) => name.clone(),
             None => {
-                warn!("Document format error: Document text found outside of any document group",);
+                self.report(WarningKind::ContentOutsideDocument);
                 return;
             }
         };
@@ -457,7 +607,7 @@ impl GroupState {
         let dest_name = match self.get_destination_name() {
             Some(name) => name.clone(),
             None => {
-                warn!("Document format error: Document text found outside of any document group",);
+                self.report(WarningKind::ContentOutsideDocument);
                 return;
             }
         };
@@ -470,7 +620,7 @@ impl GroupState {
         let dest_name = match self.get_destination_name() {
             Some(name) => name.clone(),
             None => {
-                warn!("Document format error: Document text found outside of any document group",);
+                self.report(WarningKind::ContentOutsideDocument);
                 return;
             }
         };
@@ -483,7 +633,7 @@ impl GroupState {
         let dest_name = match self.get_destination_name() {
             Some(name) => name.clone(),
             None => {
-                warn!("Document format error: Document text found outside of any document group",);
+                self.report(WarningKind::ContentOutsideDocument);
                 return;
             }
         };
@@ -496,7 +646,7 @@ impl GroupState {
         let dest_name = match self.get_destination_name() {
             Some(name) => name.clone(),
             None => {
-                warn!("Document format error: Document text found outside of any document group",);
+                self.report(WarningKind::ContentOutsideDocument);
                 return;
             }
         };
@@ -509,7 +659,7 @@ impl GroupState {
         let dest_name = match self.get_destination_name() {
             Some(name) => name.clone(),
             None => {
-                warn!("Document format error: Document text found outside of any document group",);
+                self.report(WarningKind::ContentOutsideDocument);
                 return;
             }
         };
@@ -519,10 +669,9 @@ impl GroupState {
         }
     }
     pub fn set_value(&mut self, name: &str, value: Option<i32>) {
+        // character formatting lives in `char_props`, the font table still reads "f"
+        self.char_props.apply(name, value);
         match name {
-            "f" => {
-                self.cur_font = Some(value.unwrap_or(1));
-            }
             "pard" => {
                 self.reset_paragraph_properies();
             }
@@ -534,6 +683,12 @@ impl GroupState {
             "lastrow" => {
                 self.set_row_last();
             }
+            // inside the stylesheet \tsN numbers the style itself
+            "ts" if self.get_destination_name().as_deref() != Some("stylesheet") => {
+                if let Some(number) = value {
+                    self.set_table_style(number)
+                }
+            }
             "trbrdrt" => self.border_select = BorderSelect::RowTop,
             "trbrdrl" => self.border_select = BorderSelect::RowLeft,
             "trbrdrb" => self.border_select = BorderSelect::RowBottom,
@@ -544,102 +699,91 @@ impl GroupState {
             "clbrdrl" => self.border_select = BorderSelect::CellLeft,
             "clbrdrb" => self.border_select = BorderSelect::CellBottom,
             "clbrdrr" => self.border_select = BorderSelect::CellRight,
-            "brdrs" => self.set_border_type(BorderType::SingleThickness),
-            "brdrth" => self.set_border_type(BorderType::DoubleThickness),
-            "brdrsh" => self.set_border_type(BorderType::Shadowed),
-            "brdrdb" => self.set_border_type(BorderType::Double),
-            "brdrdot" => self.set_border_type(BorderType::Dotted),
-            "brdrdash" => self.set_border_type(BorderType::Dashed),
-            "brdrhair" => self.set_border_type(BorderType::Hairline),
-            "brdrnone" => self.set_border_type(BorderType::None),
-            "brdrw" => self.set_border_width(value.unwrap_or(0) as usize),
+            "pgbrdrt" => self.border_select = BorderSelect::PageTop,
+            "pgbrdrl" => self.border_select = BorderSelect::PageLeft,
+            "pgbrdrb" => self.border_select = BorderSelect::PageBottom,
+            "pgbrdrr" => self.border_select = BorderSelect::PageRight,
+            "brdrw" => self.set_border_width(value.unwrap_or(0).max(0) as usize),
+            "brdrcf" => {
+                let color = value.unwrap_or(0).max(0) as usize;
+                self.update_border(|border| border.color = color)
+            }
+            "brsp" => {
+                let space = value.unwrap_or(0).max(0) as usize;
+                self.update_border(|border| border.space = space)
+            }
+            "brdrt" => self.select_paragraph_border(BorderSelect::ParagraphTop),
+            "brdrl" => self.select_paragraph_border(BorderSelect::ParagraphLeft),
+            "brdrb" => self.select_paragraph_border(BorderSelect::ParagraphBottom),
+            "brdrr" => self.select_paragraph_border(BorderSelect::ParagraphRight),
+            "brdrbtw" => self.select_paragraph_border(BorderSelect::ParagraphBetween),
+            "brdrbar" => self.select_paragraph_border(BorderSelect::ParagraphBar),
+            "box" => self.select_paragraph_border(BorderSelect::ParagraphBox),
+            "shading" => self.shading_mut().percent = value.unwrap_or(0).clamp(0, 10000),
+            "cfpat" => self.shading_mut().foreground_color = value.unwrap_or(0).max(0) as usize,
+            "cbpat" => self.shading_mut().background_color = value.unwrap_or(0).max(0) as usize,
+            "clmgf" => self.set_cell_horiz_merge_root(),
+            "clmrg" => self.set_cell_horiz_merged_cell(),
             "clvmgf" => self.set_cell_vert_merge_root(),
             "clvmrg" => self.set_cell_vert_merged_cell(),
             "clvertalt" => self.set_cell_vert_align(CellVerticalAlignment::Top),
             "clvertalc" => self.set_cell_vert_align(CellVerticalAlignment::Center),
             "clvertalb" => self.set_cell_vert_align(CellVerticalAlignment::Bottom),
             "fittext" => self.fit_text(value.unwrap_or(-1)),
+            "tqc" => self.next_tab.align = TabAlign::Center,
+            "tqr" => self.next_tab.align = TabAlign::Right,
+            "tqdec" => self.next_tab.align = TabAlign::Decimal,
+            "tldot" => self.next_tab.leader = TabLeader::Dot,
+            "tlmdot" => self.next_tab.leader = TabLeader::MiddleDot,
+            "tlhyph" => self.next_tab.leader = TabLeader::Hyphen,
+            "tlul" => self.next_tab.leader = TabLeader::Underline,
+            "tlth" => self.next_tab.leader = TabLeader::Thick,
+            "tleq" => self.next_tab.leader = TabLeader::Equal,
+            "tx" | "tb" => {
+                let mut tab = std::mem::take(&mut self.next_tab);
+                tab.position = value.unwrap_or(0);
+                if name == "tb" {
+                    tab.align = TabAlign::Bar;
+                }
+                self.tab_stops.push(tab);
+            }
             "cellx" => {
                 if let Some(value) = value {
-                    self.set_cell_right((value as usize).into())
+                    self.set_cell_right((value.max(0) as usize).into())
                 }
             }
             "uc" => {
                 self.flush();
-                self.ignore_count = value.unwrap_or(0) as usize;
+                self.unicode_skip = value.unwrap_or(1).max(0) as usize;
             }
             "u" => {
                 if let Some(value) = value {
                     self.write_unicode(value)
                 }
-                self.ignore_count = self.values.get("uc").unwrap_or(&Some(0)).unwrap_or(0) as usize;
-
-                /*if self.unicode_count > 0 {
-                    if let Some(value) = value {
-                        self.write_unicode(value)
-                    }
-                    self.unicode_count = self.unicode_count - 1;
-                    self.ignore_count = self.ignore_count + 1;
-                    if self.unicode_count == 0 {
-                        self.new_line();
-                    }
-                }*/
+                self.ignore_count = self.unicode_skip;
             }
             "red" => {
-                let len = self.colors.len();
-                if let Some(color) = self.colors.get_mut(len - 1) {
-                    if let Some(value) = value {
-                        color.r = value as u8;
-                    }
+                if let (Some(color), Some(value)) = (self.colors.back_mut(), value) {
+                    color.r = value as u8;
                 }
             }
             "green" => {
-                let len = self.colors.len();
-                if let Some(color) = self.colors.get_mut(len - 1) {
-                    if let Some(value) = value {
-                        color.g = value as u8;
-                    }
+                if let (Some(color), Some(value)) = (self.colors.back_mut(), value) {
+                    color.g = value as u8;
                 }
             }
             "blue" => {
-                let len = self.colors.len();
-                if let Some(color) = self.colors.get_mut(len - 1) {
-                    if let Some(value) = value {
-                        color.b = value as u8;
-                    }
+                if let (Some(color), Some(value)) = (self.colors.back_mut(), value) {
+                    color.b = value as u8;
                 }
             }
-            "b" => {
-                if let Some(n) = value {
-                    if n == 0 {
-                        self.new_line();
-                        self.values.remove("b");
-                        return;
-                    }
+            _ => {
+                if let Some(border_type) = BorderType::from_control_word(name, value) {
+                    self.set_border_type(border_type);
+                } else if let Some(pattern) = ShadingPattern::from_control_word(name) {
+                    self.shading_mut().pattern = pattern;
                 }
             }
-            "ul" => {
-                if let Some(n) = value {
-                    if n == 0 {
-                        self.new_line();
-                        self.values.remove("ul");
-
-                        return;
-                    }
-                }
-            }
-            "i" => {
-                if let Some(n) = value {
-                    if n == 0 {
-                        self.new_line();
-                        self.values.remove("i");
-
-                        return;
-                    }
-                }
-            }
-
-            _ => {}
         };
         self.values.insert(name.to_string(), value);
     }
@@ -11,109 +11,116 @@ mod style;
 mod table;
 mod table_border;
 mod text;
+mod tokenizer;
+mod warning;
 
 use std::cell::RefCell;
 use std::rc::Rc;
 
 //use encoding_rs;
-use rtf_grimoire::tokenizer::parse as parse_tokens;
+use nom::types::CompleteByteSlice;
+use rtf_grimoire::tokenizer::read_token;
 use rtf_grimoire::tokenizer::Token;
 use std::collections::HashMap;
+use std::io::Read;
 
 pub use crate::errors::*;
-use color::*;
-use destination::*;
 use document::*;
-use font::*;
 use group::*;
-use style::*;
-use table::*;
 use table_border::*;
-use text::*;
+use tokenizer::Tokenizer;
+
+pub use color::Color;
+pub use destination::Destination;
+pub use document::{Document, DocumentDefaults, DocumentInfo, DocumentTime};
+use font::{language_codepage, language_tag};
+pub use font::{
+    AssociatedFonts, CharacterProperties, Charset, Font, FontClass, FontFamily, FontStyle,
+    ThemeFont, UnderlineType,
+};
+pub use style::{
+    Align, ParagraphShading, ParagraphStyle, RunFormat, ShadingPattern, StyleKind, StyleSheet,
+    TabAlign, TabLeader, TabStop,
+};
+pub use table::{CellVerticalAlignment, Table, TableCell, TableCellOption, TableRow, Twips};
+pub use table_border::{Border, BorderType, CellBorder, PageBorder, ParagraphBorder, RowBorder};
+pub use text::{Line, Page, Paragraph, Section, Text};
+use warning::Reporter;
+pub use warning::{Diagnostics, ParseOptions, Warning, WarningKind};
 
 pub struct Rtf {
-    tokens: Vec<Token>,
+    /// tokens with the byte offset they start at
+    tokens: Vec<(usize, Token)>,
 }
 
 impl Rtf {
-    fn tokenize(data: &[u8]) -> Result<Vec<Token>, Errors> {
-        parse_tokens(data).map_err(|_| Errors::ParseError)
-    }
     pub fn from_base64(data: &str) -> Result<Self, Errors> {
-        let bytes = base64::decode(data).map_err(|e| Errors::Base64DecodeError(e))?;
+        let bytes = base64::decode(data)?;
         Self::from_bytes(&bytes)
     }
     pub fn from_bytes(data: &[u8]) -> Result<Self, Errors> {
         Ok(Self {
-            tokens: Rtf::tokenize(data)?,
+            tokens: Tokenizer::new(data).collect::<Result<_, _>>()?,
         })
     }
-    pub fn get_text(
-        self,
-    ) -> (
-        Option<Text>,
-        HashMap<i32, Font>,
-        HashMap<i32, StyleSheet>,
-        Vec<Color>,
-        Option<i32>,
-    ) {
-        let mut state = DocumentState::new();
+    /// Parses a document while reading it, without holding all of the input or its tokens.
+    ///
+    /// Parses leniently like [`Rtf::parse`]; only unreadable input is an error.
+    pub fn from_reader(reader: impl Read) -> Result<Document, Errors> {
+        Self::from_reader_with_options(reader, &ParseOptions::default())
+    }
+    /// Same as [`Rtf::from_reader`], with the options of [`Rtf::parse_with_options`]
+    pub fn from_reader_with_options(
+        reader: impl Read,
+        options: &ParseOptions,
+    ) -> Result<Document, Errors> {
+        Self::process(Tokenizer::new(reader), options)
+    }
+    /// Processes the token stream into a [`Document`] that any renderer can borrow.
+    ///
+    /// Parses leniently: malformed constructs are skipped and listed in [`Document::diagnostics`].
+    pub fn parse(&self) -> Document {
+        let mut state = DocumentState::new(&ParseOptions::default());
 
-        for token in self.tokens.iter().filter(|c| c != &&Token::Newline) {
-            state.process_token(token);
-        }
-        let font_table = state.fonts;
-        let stylesheets = state.stylesheets;
-        let dest = (*state.destinations).borrow();
-        let color_table = state.colors;
-        let default_font_number = state.default_font_number;
-        if let Some(dest) = dest.get("rtf") {
-            debug!("Writing rtf1 content...");
-            if let Destination::Text(text) = dest {
-                (
-                    Some(text.clone()),
-                    font_table,
-                    stylesheets,
-                    color_table,
-                    default_font_number,
-                )
-            } else {
-                (
-                    None,
-                    font_table,
-                    stylesheets,
-                    color_table,
-                    default_font_number,
-                )
-            }
-        } else {
-            (
-                None,
-                font_table,
-                stylesheets,
-                color_table,
-                default_font_number,
-            )
+        for (offset, token) in self.tokens.iter().filter(|(_, c)| c != &Token::Newline) {
+            state.process_token(*offset, token);
         }
+        state.finish()
     }
-    pub fn into_text(self) -> String {
-        let mut state = DocumentState::new();
+    /// Same as [`Rtf::parse`], but in strict mode the first malformed construct
+    /// is returned as [`Errors::ValidationError`]
+    pub fn parse_with_options(&self, options: &ParseOptions) -> Result<Document, Errors> {
+        Self::process(
+            self.tokens
+                .iter()
+                .map(|(offset, token)| Ok((*offset, token))),
+            options,
+        )
+    }
+    fn process<I, T>(tokens: I, options: &ParseOptions) -> Result<Document, Errors>
+    where
+        I: Iterator<Item = Result<(usize, T), Errors>>,
+        T: std::borrow::Borrow<Token>,
+    {
+        let mut state = DocumentState::new(options);
 
-        for token in self.tokens.iter().filter(|c| c != &&Token::Newline) {
-            state.process_token(token);
-        }
-        let dest = (*state.destinations).borrow();
-        if let Some(dest) = dest.get("rtf") {
-            debug!("Writing rtf1 content...");
-            if let Destination::Text(text) = dest {
-                text.to_string()
-            } else {
-                let bytes = dest.as_bytes();
-                let (cow, _used_encoding, _has_error) = encoding_rs::SHIFT_JIS.decode(&bytes);
-                cow.into_owned()
+        for token in tokens {
+            let (offset, token) = token?;
+            if token.borrow() == &Token::Newline {
+                continue;
+            }
+            state.process_token(offset, token.borrow());
+            if let Some(error) = state.error() {
+                return Err(error);
             }
-        } else {
-            "".to_owned()
         }
+        state.end_input();
+        if let Some(error) = state.error() {
+            return Err(error);
+        }
+        Ok(state.finish())
+    }
+    pub fn into_text(self) -> String {
+        self.parse().to_text()
     }
 }
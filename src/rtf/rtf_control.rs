@@ -24,30 +24,30 @@ lazy_static! {
         m.insert("atntime", Box::new(destination_control_set_state_default));
         m.insert("atrfend", Box::new(destination_control_set_state_default));
         m.insert("atrfstart", Box::new(destination_control_set_state_default));
-        m.insert("author", Box::new(destination_control_set_state_default));
+        m.insert("author", Box::new(destination_control_set_state_encoding));
         m.insert("background", Box::new(destination_control_set_state_default));
         m.insert("bkmkend", Box::new(destination_control_set_state_default));
         m.insert("bkmkstart", Box::new(destination_control_set_state_default));
         m.insert("blipuid", Box::new(destination_control_set_state_default));
         m.insert("buptim", Box::new(destination_control_set_state_default));
-        m.insert("category", Box::new(destination_control_set_state_default));
+        m.insert("category", Box::new(destination_control_set_state_encoding));
         m.insert("colorschememapping", Box::new(destination_control_set_state_default));
         m.insert("colortbl", Box::new(destination_control_set_state_default));
-        m.insert("comment", Box::new(destination_control_set_state_default));
-        m.insert("company", Box::new(destination_control_set_state_default));
+        m.insert("comment", Box::new(destination_control_set_state_encoding));
+        m.insert("company", Box::new(destination_control_set_state_encoding));
         m.insert("creatim", Box::new(destination_control_set_state_default));
         m.insert("datafield", Box::new(destination_control_set_state_default));
         m.insert("datastore", Box::new(destination_control_set_state_default));
         m.insert("defchp", Box::new(destination_control_set_state_default));
         m.insert("defpap", Box::new(destination_control_set_state_default));
         m.insert("do", Box::new(destination_control_set_state_default));
-        m.insert("doccomm", Box::new(destination_control_set_state_default));
+        m.insert("doccomm", Box::new(destination_control_set_state_encoding));
         m.insert("docvar", Box::new(destination_control_set_state_default));
         m.insert("dptxbxtext", Box::new(destination_control_set_state_default));
         m.insert("ebcend", Box::new(destination_control_set_state_default));
         m.insert("ebcstart", Box::new(destination_control_set_state_default));
         m.insert("factoidname", Box::new(destination_control_set_state_default));
-        m.insert("falt", Box::new(destination_control_set_state_default));
+        m.insert("falt", Box::new(destination_control_set_state_encoding));
         m.insert("fchars", Box::new(destination_control_set_state_default));
         m.insert("ffdeftext", Box::new(destination_control_set_state_default));
         m.insert("ffentrymcr", Box::new(destination_control_set_state_default));
@@ -85,14 +85,14 @@ lazy_static! {
         m.insert("headerr", Box::new(destination_control_set_state_default));
         m.insert("hl", Box::new(destination_control_set_state_default));
         m.insert("hlfr", Box::new(destination_control_set_state_default));
-        m.insert("hlinkbase", Box::new(destination_control_set_state_default));
+        m.insert("hlinkbase", Box::new(destination_control_set_state_encoding));
         m.insert("hlloc", Box::new(destination_control_set_state_default));
         m.insert("hlsrc", Box::new(destination_control_set_state_default));
         m.insert("hsv", Box::new(destination_control_set_state_default));
         m.insert("htmltag", Box::new(destination_control_set_state_default));
         m.insert("info", Box::new(destination_control_set_state_default));
         m.insert("keycode", Box::new(destination_control_set_state_default));
-        m.insert("keywords", Box::new(destination_control_set_state_default));
+        m.insert("keywords", Box::new(destination_control_set_state_encoding));
         m.insert("latentstyles", Box::new(destination_control_set_state_default));
         m.insert("lchars", Box::new(destination_control_set_state_default));
         m.insert("levelnumbers", Box::new(destination_control_set_state_default));
@@ -114,7 +114,7 @@ lazy_static! {
         m.insert("mailmerge", Box::new(destination_control_set_state_default));
         m.insert("maln", Box::new(destination_control_set_state_default));
         m.insert("malnScr", Box::new(destination_control_set_state_default));
-        m.insert("manager", Box::new(destination_control_set_state_default));
+        m.insert("manager", Box::new(destination_control_set_state_encoding));
         m.insert("margPr", Box::new(destination_control_set_state_default));
         m.insert("mbar", Box::new(destination_control_set_state_default));
         m.insert("mbarPr", Box::new(destination_control_set_state_default));
@@ -247,8 +247,8 @@ lazy_static! {
         m.insert("oldsprops", Box::new(destination_control_set_state_default));
         m.insert("oldtprops", Box::new(destination_control_set_state_default));
         m.insert("oleclsid", Box::new(destination_control_set_state_default));
-        m.insert("operator", Box::new(destination_control_set_state_default));
-        m.insert("panose", Box::new(destination_control_set_state_default));
+        m.insert("operator", Box::new(destination_control_set_state_encoding));
+        m.insert("panose", Box::new(destination_control_set_state_encoding));
         m.insert("password", Box::new(destination_control_set_state_default));
         m.insert("passwordhash", Box::new(destination_control_set_state_default));
         m.insert("pgp", Box::new(destination_control_set_state_default));
@@ -286,14 +286,14 @@ lazy_static! {
         m.insert("sn", Box::new(destination_control_set_state_default));
         m.insert("sp", Box::new(destination_control_set_state_default));
         m.insert("staticval", Box::new(destination_control_set_state_default));
-        m.insert("stylesheet", Box::new(destination_control_set_state_default));
-        m.insert("subject", Box::new(destination_control_set_state_default));
+        m.insert("stylesheet", Box::new(destination_control_set_state_encoding));
+        m.insert("subject", Box::new(destination_control_set_state_encoding));
         m.insert("sv", Box::new(destination_control_set_state_default));
         m.insert("svb", Box::new(destination_control_set_state_default));
         m.insert("tc", Box::new(destination_control_set_state_default));
         m.insert("template", Box::new(destination_control_set_state_default));
         m.insert("themedata", Box::new(destination_control_set_state_default));
-        m.insert("title", Box::new(destination_control_set_state_default));
+        m.insert("title", Box::new(destination_control_set_state_encoding));
         m.insert("txe", Box::new(destination_control_set_state_default));
         m.insert("ud", Box::new(destination_control_set_state_default));
         m.insert("upr", Box::new(destination_control_set_state_default));
@@ -336,7 +336,7 @@ lazy_static! {
         m.insert("}", Box::new(control_symbol_write_ansi_char));
         m.insert("~", Box::new(control_symbol_write_ansi_char));
         m.insert("bullet", Box::new(control_symbol_write_ansi_char));
-        m.insert("cell", Box::new(control_value_set_state_and_write_ansi_char));
+        m.insert("cell", Box::new(control_symbol_write_ansi_char));
         m.insert("chatn", Box::new(control_word_ignore));
         m.insert("chdate", Box::new(control_word_ignore));
         m.insert("chdpa", Box::new(control_word_ignore));
@@ -361,7 +361,7 @@ lazy_static! {
         m.insert("par", Box::new(control_symbol_write_ansi_char));
         m.insert("qmspace", Box::new(control_word_ignore));
         m.insert("rdblquote", Box::new(control_symbol_write_ansi_char));
-        m.insert("row", Box::new(control_value_set_state_and_write_ansi_char));
+        m.insert("row", Box::new(control_symbol_write_ansi_char));
         m.insert("rquote", Box::new(control_symbol_write_ansi_char));
         m.insert("rtlmark", Box::new(control_word_ignore));
         m.insert("sect", Box::new(control_symbol_write_ansi_char));
@@ -1941,7 +1941,7 @@ fn control_flag_set_state_encoding(state: &mut GroupState, name: &str, arg: Opti
             state.set_codepage(10000u16)
         }
         _ => {
-            panic!("Programmer error: {} was indicated as an encoding-related control flag, without adding an encoding mapping for it.", name)
+            warn!("{} was indicated as an encoding-related control flag, without adding an encoding mapping for it.", name)
         }
     }
     state.set_value(name, arg);
@@ -1955,7 +1955,7 @@ fn control_value_set_state_encoding(state: &mut GroupState, name: &str, arg: Opt
     match name {
         "ansicpg" => state.set_codepage(arg.unwrap_or(1252i32) as u16),
         _ => {
-            panic!("Programmer error: {} was indicated as an encoding-related control value, without adding an encoding mapping for it.", name)
+            warn!("{} was indicated as an encoding-related control value, without adding an encoding mapping for it.", name)
         }
     }
 
@@ -1966,60 +1966,50 @@ fn control_word_ignore(_state: &mut GroupState, name: &str, _arg: Option<i32>) {
     trace!("Ignoring control word {}", name);
 }
 
-fn control_value_set_state_and_write_ansi_char(
-    state: &mut GroupState,
-    name: &str,
-    arg: Option<i32>,
-) {
-    let encoding = state.get_encoding();
-
-    control_symbol_write_ansi_char(state, name, arg);
-    control_value_set_state_encoding(state, "ansicpg", Some(932));
-    state.set_encoding(encoding);
-}
-
 fn control_symbol_write_ansi_char(state: &mut GroupState, name: &str, arg: Option<i32>) {
-    let arg_byte = arg.map(|n| [(n & 0xFF) as u8]).unwrap_or([0u8]);
-    let opt_bytes: Option<&[u8]> = match name {
-        "'" => {
-            debug!("control symbol: ansi byte {:?}", arg_byte);
-            Some(&arg_byte) // ANSI hex escape
-        }
-        "\"" => Some(b"\""), // Referenced, but not formally defined mapping in spec
-        "\\" => Some(b"\\"),
-        "_" => Some(b"-"), // Non-breaking hyphen
-        "{" => Some(b"{"),
-        "}" => Some(b"}"),
-        "~" => Some(b" "),         // Non-breaking space
-        "bullet" => Some(b"\x95"), // Pre-defined ANSI mapping in spec
-        "emdash" => Some(b"\x97"), // Pre-defined ANSI mapping in spec
-        "emspace" => Some(b"  "),
-        "enspace" => Some(b" "),
-        "endash" => Some(b"\x96"),    // Pre-defined ANSI mapping in spec
-        "ldblquote" => Some(b"\x93"), // Pre-defined ANSI mapping in spec
-        "line" => Some(b"\n"),
-        "lquote" => Some(b"\x91"), // Pre-defined ANSI mapping in spec
-        "page" => Some(b"\n\n"),
-        "par" => Some(b"\n"),
-        "rdblquote" => Some(b"\x94"), // Pre-defined ANSI mapping in spec
-        "rquote" => Some(b"\x92"),    // Pre-defined ANSI mapping in spec
-        "sect" => Some(b"\n\n"),
-        "tab" => Some(b"\t"),
-        "row" => Some(b"\n "),  // Unofficial mapping for ending a table row
-        "cell" => Some(b"\t"),  // Unofficial mapping for separating table row cells
-        "ls" => Some(b"\x95 "), // Unofficial mapping for list entry
-        "\n" => Some(b"\n"),    // Semi-official compatibility mapping, same as \par
-        "\r" => Some(b"\n"),    // Semi-official compatibility mapping, same as \par
-        "\t" => Some(b"\t"),    // Semi-official compatibility mapping
-        " " => Some(b" "),      // Semi-official compatibility mapping
-        "/" => Some(b"/"),      // Unsupported, but used symbol mapping
+    if name == "'" {
+        // ANSI hex escape, decoded with the rest of the buffered bytes
+        let byte = arg.map(|n| (n & 0xFF) as u8).unwrap_or(0);
+        debug!("control symbol: ansi byte {:?}", byte);
+        state.buffer(&[byte]);
+        return;
+    }
+    let opt_text: Option<&str> = match name {
+        "\"" => Some("\""), // Referenced, but not formally defined mapping in spec
+        "\\" => Some("\\"),
+        "_" => Some("-"), // Non-breaking hyphen
+        "{" => Some("{"),
+        "}" => Some("}"),
+        "~" => Some(" "),             // Non-breaking space
+        "bullet" => Some("\u{2022}"), // Pre-defined mapping in spec
+        "emdash" => Some("\u{2014}"), // Pre-defined mapping in spec
+        "emspace" => Some("  "),
+        "enspace" => Some(" "),
+        "endash" => Some("\u{2013}"),    // Pre-defined mapping in spec
+        "ldblquote" => Some("\u{201c}"), // Pre-defined mapping in spec
+        "line" => Some("\n"),
+        "lquote" => Some("\u{2018}"), // Pre-defined mapping in spec
+        "page" => Some("\n\n"),
+        "par" => Some("\n"),
+        "rdblquote" => Some("\u{201d}"), // Pre-defined mapping in spec
+        "rquote" => Some("\u{2019}"),    // Pre-defined mapping in spec
+        "sect" => Some("\n\n"),
+        "tab" => Some("\t"),
+        "row" => Some("\n "),      // Unofficial mapping for ending a table row
+        "cell" => Some("\t"),      // Unofficial mapping for separating table row cells
+        "ls" => Some("\u{2022} "), // Unofficial mapping for list entry
+        "\n" => Some("\n"),        // Semi-official compatibility mapping, same as \par
+        "\r" => Some("\n"),        // Semi-official compatibility mapping, same as \par
+        "\t" => Some("\t"),        // Semi-official compatibility mapping
+        " " => Some(" "),          // Semi-official compatibility mapping
+        "/" => Some("/"),          // Unsupported, but used symbol mapping
         _ => {
             error!("Unsupported ANSI char mapping requested: {}", name);
             None
         }
     };
 
-    if let Some(bytes) = opt_bytes {
+    if let Some(text) = opt_text {
         match name {
             "line" => state.new_paragraph(),
             "page" => state.new_page(),
@@ -2029,7 +2019,11 @@ fn control_symbol_write_ansi_char(state: &mut GroupState, name: &str, arg: Optio
             "row" => state.end_row(),
             "\n" => { /* NOP */ }
             "\r" => { /* NOP */ }
-            _ => state.buffer(&bytes),
+            _ => {
+                // already Unicode, so it must not go through the code page
+                state.flush();
+                state.write_text(text);
+            }
         }
     }
 }
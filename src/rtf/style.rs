@@ -1,40 +1,282 @@
 use super::*;
+use std::collections::HashMap;
+
+/// What a stylesheet entry applies to
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum StyleKind {
+    /// `\sN`, or no number at all
+    Paragraph,
+    /// `\*\csN`
+    Character,
+    /// `\*\tsN`
+    Table,
+    /// `\dsN`
+    Section,
+}
 
 #[derive(Clone, Debug)]
 pub struct StyleSheet {
     pub number: i32,
     pub name: String,
+    pub kind: StyleKind,
+    /// `\sbasedonN`
+    pub based_on: Option<i32>,
+    /// `\snextN`, the style of the paragraph after one in this style
+    pub next: Option<i32>,
+    /// `\slinkN`, the paragraph style linked to a character style or the other way round
+    pub link: Option<i32>,
+    /// `\sqformat`, shown in the quick style gallery
+    pub q_format: bool,
+    /// `\additive`, character formatting added to the paragraph's rather than replacing it
+    pub additive: bool,
+    /// `\ssemihidden`
+    pub semi_hidden: bool,
+    /// `\fN`
+    pub font: Option<i32>,
     pub font_style: Option<FontStyle>,
     pub para_style: Option<ParagraphStyle>,
 }
+impl StyleSheet {
+    /// This style followed by the styles it is based on, nearest first.
+    /// The chain stops at a missing style or a loop.
+    pub fn chain<'a>(&'a self, stylesheets: &'a HashMap<i32, StyleSheet>) -> Vec<&'a StyleSheet> {
+        let mut chain = vec![self];
+        let mut based_on = self.based_on;
+        while let Some(stylesheet) = based_on.and_then(|number| stylesheets.get(&number)) {
+            if chain.iter().any(|s| s.number == stylesheet.number) {
+                break;
+            }
+            chain.push(stylesheet);
+            based_on = stylesheet.based_on;
+        }
+        chain
+    }
+    /// Character formatting with whatever this style leaves unset taken from its base styles
+    pub fn resolved_font_style(&self, stylesheets: &HashMap<i32, StyleSheet>) -> Option<FontStyle> {
+        self.chain(stylesheets)
+            .into_iter()
+            .rev()
+            .filter_map(|stylesheet| stylesheet.font_style.as_ref())
+            .fold(None, |base: Option<FontStyle>, style| {
+                Some(match base {
+                    Some(base) => style.inherit(&base),
+                    None => style.clone(),
+                })
+            })
+    }
+    /// Paragraph formatting with whatever this style leaves unset taken from its base styles
+    pub fn resolved_para_style(
+        &self,
+        stylesheets: &HashMap<i32, StyleSheet>,
+    ) -> Option<ParagraphStyle> {
+        self.chain(stylesheets)
+            .into_iter()
+            .rev()
+            .filter_map(|stylesheet| stylesheet.para_style.as_ref())
+            .fold(None, |base: Option<ParagraphStyle>, style| {
+                Some(match base {
+                    Some(base) => style.inherit(&base),
+                    None => style.clone(),
+                })
+            })
+    }
+    /// The font of this style or of the nearest base style that sets one
+    pub fn resolved_font(&self, stylesheets: &HashMap<i32, StyleSheet>) -> Option<i32> {
+        self.chain(stylesheets)
+            .into_iter()
+            .find_map(|stylesheet| stylesheet.font)
+    }
+}
 impl std::default::Default for StyleSheet {
     fn default() -> StyleSheet {
         StyleSheet {
             number: 0,
             name: "Default".to_owned(),
+            kind: StyleKind::Paragraph,
+            based_on: None,
+            next: None,
+            link: None,
+            q_format: false,
+            additive: false,
+            semi_hidden: false,
+            font: None,
             font_style: None,
             para_style: None,
         }
     }
 }
-#[derive(Clone, Debug, PartialEq)]
+/// Character formatting of a run with styles and document defaults applied,
+/// see [`Document::run_format`]
+#[derive(Clone, Debug, PartialEq, Default)]
+pub struct RunFormat {
+    pub font: Option<i32>,
+    pub style: FontStyle,
+}
+
+#[derive(Clone, Debug, PartialEq, Default)]
 pub struct ParagraphStyle {
     pub align: Option<Align>,
     pub first_indent: Option<i32>,
     pub left_indent: Option<i32>,
     pub right_indent: Option<i32>,
+    /// `\sbN` in twips
+    pub space_before: Option<i32>,
+    /// `\saN` in twips
+    pub space_after: Option<i32>,
+    /// `\sbauto1`, space before chosen by the application as in HTML; `\sb` is the fallback
+    pub space_before_auto: Option<bool>,
+    /// `\saauto1`
+    pub space_after_auto: Option<bool>,
+    /// `\lisbN` in hundredths of a line
+    pub space_before_lines: Option<i32>,
+    /// `\lisaN` in hundredths of a line
+    pub space_after_lines: Option<i32>,
+    /// `\slN`: at least N twips when positive, exactly -N twips when negative, automatic when 0.
+    /// With `\slmult1` a positive N is a multiple of single spacing in 240ths
+    pub line_spacing: Option<i32>,
+    /// `\slmultN`
+    pub line_multiple: Option<bool>,
+    /// `\contextualspace`, no space between paragraphs of the same style
+    pub contextual_spacing: Option<bool>,
+    /// in the order they were given
+    pub tabs: Vec<TabStop>,
+    pub border: Option<ParagraphBorder>,
+    pub shading: Option<ParagraphShading>,
 }
-
-impl std::default::Default for ParagraphStyle {
-    fn default() -> Self {
-        Self {
-            align: None,
-            first_indent: None,
-            left_indent: None,
-            right_indent: None,
+impl ParagraphStyle {
+    /// This style with every property it leaves unset taken from `base`
+    pub fn inherit(&self, base: &ParagraphStyle) -> ParagraphStyle {
+        ParagraphStyle {
+            align: self.align.clone().or_else(|| base.align.clone()),
+            first_indent: self.first_indent.or(base.first_indent),
+            left_indent: self.left_indent.or(base.left_indent),
+            right_indent: self.right_indent.or(base.right_indent),
+            space_before: self.space_before.or(base.space_before),
+            space_after: self.space_after.or(base.space_after),
+            space_before_auto: self.space_before_auto.or(base.space_before_auto),
+            space_after_auto: self.space_after_auto.or(base.space_after_auto),
+            space_before_lines: self.space_before_lines.or(base.space_before_lines),
+            space_after_lines: self.space_after_lines.or(base.space_after_lines),
+            line_spacing: self.line_spacing.or(base.line_spacing),
+            line_multiple: self.line_multiple.or(base.line_multiple),
+            contextual_spacing: self.contextual_spacing.or(base.contextual_spacing),
+            tabs: if self.tabs.is_empty() {
+                base.tabs.clone()
+            } else {
+                self.tabs.clone()
+            },
+            border: self.border.clone().or_else(|| base.border.clone()),
+            shading: self.shading.clone().or_else(|| base.shading.clone()),
         }
     }
 }
+
+/// Alignment of text at a tab stop
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum TabAlign {
+    #[default]
+    Left,
+    /// `\tqc`
+    Center,
+    /// `\tqr`
+    Right,
+    /// `\tqdec`
+    Decimal,
+    /// `\tbN`, a vertical bar rather than a stop
+    Bar,
+}
+
+/// What fills the space before a tab stop
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum TabLeader {
+    #[default]
+    None,
+    /// `\tldot`
+    Dot,
+    /// `\tlmdot`
+    MiddleDot,
+    /// `\tlhyph`
+    Hyphen,
+    /// `\tlul`
+    Underline,
+    /// `\tlth`
+    Thick,
+    /// `\tleq`
+    Equal,
+}
+
+/// `\txN` or `\tbN` with the alignment and leader words before it
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct TabStop {
+    /// from the left indent, in twips
+    pub position: i32,
+    pub align: TabAlign,
+    pub leader: TabLeader,
+}
+
+/// Hatching of paragraph shading, from the `\bg...` words
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum ShadingPattern {
+    #[default]
+    None,
+    /// `\bghoriz`
+    Horizontal,
+    /// `\bgvert`
+    Vertical,
+    /// `\bgfdiag`, top left to bottom right
+    ForwardDiagonal,
+    /// `\bgbdiag`, bottom left to top right
+    BackwardDiagonal,
+    /// `\bgcross`
+    Cross,
+    /// `\bgdcross`
+    DiagonalCross,
+    /// `\bgdkhoriz`
+    DarkHorizontal,
+    /// `\bgdkvert`
+    DarkVertical,
+    /// `\bgdkfdiag`
+    DarkForwardDiagonal,
+    /// `\bgdkbdiag`
+    DarkBackwardDiagonal,
+    /// `\bgdkcross`
+    DarkCross,
+    /// `\bgdkdcross`
+    DarkDiagonalCross,
+}
+impl ShadingPattern {
+    pub fn from_control_word(name: &str) -> Option<Self> {
+        let pattern = match name {
+            "bghoriz" => Self::Horizontal,
+            "bgvert" => Self::Vertical,
+            "bgfdiag" => Self::ForwardDiagonal,
+            "bgbdiag" => Self::BackwardDiagonal,
+            "bgcross" => Self::Cross,
+            "bgdcross" => Self::DiagonalCross,
+            "bgdkhoriz" => Self::DarkHorizontal,
+            "bgdkvert" => Self::DarkVertical,
+            "bgdkfdiag" => Self::DarkForwardDiagonal,
+            "bgdkbdiag" => Self::DarkBackwardDiagonal,
+            "bgdkcross" => Self::DarkCross,
+            "bgdkdcross" => Self::DarkDiagonalCross,
+            _ => return None,
+        };
+        Some(pattern)
+    }
+}
+
+/// Paragraph shading
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ParagraphShading {
+    /// `\shadingN` in hundredths of a percent of the pattern color, 0 to 10000
+    pub percent: i32,
+    pub pattern: ShadingPattern,
+    /// `\cfpatN`, color of the pattern, index into the color table
+    pub foreground_color: usize,
+    /// `\cbpatN`, color under the pattern, index into the color table
+    pub background_color: usize,
+}
+
 #[derive(Clone, Debug, PartialEq)]
 pub enum Align {
     Left,
@@ -16,6 +16,12 @@ pub enum CellVerticalAlignment {
     Center,
     Bottom,
 }
+impl Default for TableCellOption {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl TableCellOption {
     pub fn new() -> Self {
         Self {
@@ -34,6 +40,12 @@ pub struct TableCell {
     pub paras: Vec<Paragraph>,
     pub opts: TableCellOption,
 }
+impl Default for TableCell {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl TableCell {
     pub fn new() -> TableCell {
         TableCell {
@@ -53,7 +65,15 @@ pub struct TableRow {
     pub cell_opt_pos: usize,
     pub cell_opts: Vec<TableCellOption>,
     pub is_last: bool,
+    /// `\tsN` table style
+    pub style: Option<i32>,
 }
+impl Default for TableRow {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl TableRow {
     pub fn new() -> TableRow {
         TableRow {
@@ -62,6 +82,7 @@ impl TableRow {
             cell_opt_pos: 0,
             cell_opts: vec![TableCellOption::new()],
             is_last: false,
+            style: None,
         }
     }
     pub fn add_cell(&mut self) {
@@ -85,6 +106,12 @@ impl TableRow {
 pub struct Table {
     pub rows: Vec<TableRow>,
 }
+impl Default for Table {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl Table {
     pub fn new() -> Table {
         Table {
@@ -109,18 +136,32 @@ impl Table {
         let row = self.last_row();
         row.add_cell();
     }
+    /// Every distinct `\cellx` boundary of the rows in twips, ascending and
+    /// starting with the left edge at 0
+    pub fn cell_boundaries(&self) -> Vec<usize> {
+        let mut boundaries: Vec<usize> = self
+            .rows
+            .iter()
+            .flat_map(|row| row.cells.iter())
+            .filter_map(|cell| cell.opts.right.clone().map(usize::from))
+            .collect();
+        boundaries.push(0);
+        boundaries.sort_unstable();
+        boundaries.dedup();
+        boundaries
+    }
 }
 
 #[derive(Clone, Debug, PartialEq)]
 pub struct Twips(usize);
 impl From<usize> for Twips {
     fn from(n: usize) -> Twips {
-        Self { 0: n }
+        Self(n)
     }
 }
-impl Into<usize> for Twips {
-    fn into(self) -> usize {
-        self.0
+impl From<Twips> for usize {
+    fn from(val: Twips) -> Self {
+        val.0
     }
 }
 impl Twips {
@@ -128,6 +169,6 @@ impl Twips {
         self.0 / 15
     }
     pub fn from_px(px: usize) -> Self {
-        Self { 0: px * 15 }
+        Self(px * 15)
     }
 }
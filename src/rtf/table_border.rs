@@ -1,5 +1,4 @@
-pub use super::*;
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq)]
 pub enum BorderType {
     None,
     SingleThickness,
@@ -9,17 +8,93 @@ pub enum BorderType {
     Dotted,
     Dashed,
     Hairline,
+    Inset,
+    Outset,
+    DashSmall,
+    DotDash,
+    DotDotDash,
+    Triple,
+    ThickThinSmall,
+    ThinThickSmall,
+    ThinThickThinSmall,
+    ThickThinMedium,
+    ThinThickMedium,
+    ThinThickThinMedium,
+    ThickThinLarge,
+    ThinThickLarge,
+    ThinThickThinLarge,
+    Wavy,
+    DoubleWavy,
+    /// `\brdrdashdotstr`, striped
+    Striped,
+    Emboss,
+    Engrave,
+    Frame,
+    /// `\brdrartN`, one of the art borders by number
+    Art(i32),
 }
-#[derive(Clone, Debug)]
+impl BorderType {
+    /// The border style a `\brdr...` control word sets
+    pub fn from_control_word(name: &str, value: Option<i32>) -> Option<BorderType> {
+        let border_type = match name {
+            "brdrs" => BorderType::SingleThickness,
+            "brdrth" => BorderType::DoubleThickness,
+            "brdrsh" => BorderType::Shadowed,
+            "brdrdb" => BorderType::Double,
+            "brdrdot" => BorderType::Dotted,
+            "brdrdash" => BorderType::Dashed,
+            "brdrhair" => BorderType::Hairline,
+            "brdrinset" => BorderType::Inset,
+            "brdroutset" => BorderType::Outset,
+            "brdrdashsm" => BorderType::DashSmall,
+            "brdrdashd" | "brdrdashdot" => BorderType::DotDash,
+            "brdrdashdd" | "brdrdashdotdot" => BorderType::DotDotDash,
+            "brdrtriple" => BorderType::Triple,
+            "brdrthtnsg" => BorderType::ThickThinSmall,
+            "brdrtnthsg" => BorderType::ThinThickSmall,
+            "brdrtnthtnsg" => BorderType::ThinThickThinSmall,
+            "brdrthtnmg" => BorderType::ThickThinMedium,
+            "brdrtnthmg" => BorderType::ThinThickMedium,
+            "brdrtnthtnmg" => BorderType::ThinThickThinMedium,
+            "brdrthtnlg" => BorderType::ThickThinLarge,
+            "brdrtnthlg" => BorderType::ThinThickLarge,
+            "brdrtnthtnlg" => BorderType::ThinThickThinLarge,
+            "brdrwavy" => BorderType::Wavy,
+            "brdrwavydb" => BorderType::DoubleWavy,
+            "brdrdashdotstr" => BorderType::Striped,
+            "brdremboss" => BorderType::Emboss,
+            "brdrengrave" => BorderType::Engrave,
+            "brdrframe" => BorderType::Frame,
+            "brdrart" => BorderType::Art(value.unwrap_or(0)),
+            "brdrnone" | "brdrnil" => BorderType::None,
+            _ => return None,
+        };
+        Some(border_type)
+    }
+}
+#[derive(Clone, Debug, PartialEq)]
 pub struct Border {
     pub border_type: BorderType,
+    /// `\brdrwN` in twips
     pub width: usize,
+    /// `\brdrcfN`, index into the color table, 0 for automatic
+    pub color: usize,
+    /// `\brspN`, distance from the text in twips
+    pub space: usize,
 }
+impl Default for Border {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl Border {
     pub fn new() -> Border {
         Border {
             border_type: BorderType::None,
             width: 0,
+            color: 0,
+            space: 0,
         }
     }
 }
@@ -30,6 +105,12 @@ pub struct CellBorder {
     pub bottom: Option<Border>,
     pub right: Option<Border>,
 }
+impl Default for CellBorder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl CellBorder {
     pub fn new() -> CellBorder {
         CellBorder {
@@ -49,6 +130,12 @@ pub struct RowBorder {
     pub horizontal: Option<Border>,
     pub vertical: Option<Border>,
 }
+impl Default for RowBorder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl RowBorder {
     pub fn new() -> RowBorder {
         RowBorder {
@@ -62,6 +149,53 @@ impl RowBorder {
     }
 }
 
+/// Borders of a paragraph, from `\brdrt`, `\brdrl`, `\brdrb`, `\brdrr`, `\brdrbtw`,
+/// `\brdrbar` and `\box`
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ParagraphBorder {
+    pub top: Option<Border>,
+    pub left: Option<Border>,
+    pub bottom: Option<Border>,
+    pub right: Option<Border>,
+    /// between consecutive paragraphs with the same borders
+    pub between: Option<Border>,
+    /// vertical bar beside the paragraph
+    pub bar: Option<Border>,
+}
+impl ParagraphBorder {
+    /// The sides `select` refers to, created as needed
+    pub fn sides_mut(&mut self, select: &BorderSelect) -> Vec<&mut Border> {
+        let sides = match select {
+            BorderSelect::ParagraphTop => vec![&mut self.top],
+            BorderSelect::ParagraphLeft => vec![&mut self.left],
+            BorderSelect::ParagraphBottom => vec![&mut self.bottom],
+            BorderSelect::ParagraphRight => vec![&mut self.right],
+            BorderSelect::ParagraphBetween => vec![&mut self.between],
+            BorderSelect::ParagraphBar => vec![&mut self.bar],
+            BorderSelect::ParagraphBox => vec![
+                &mut self.top,
+                &mut self.left,
+                &mut self.bottom,
+                &mut self.right,
+            ],
+            _ => vec![],
+        };
+        sides
+            .into_iter()
+            .map(|side| side.get_or_insert_with(Border::new))
+            .collect()
+    }
+}
+
+/// Page borders of a section, from `\pgbrdrt`, `\pgbrdrl`, `\pgbrdrb` and `\pgbrdrr`
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct PageBorder {
+    pub top: Option<Border>,
+    pub left: Option<Border>,
+    pub bottom: Option<Border>,
+    pub right: Option<Border>,
+}
+
 #[derive(Clone)]
 pub enum BorderSelect {
     RowTop,
@@ -74,5 +208,17 @@ pub enum BorderSelect {
     CellLeft,
     CellRight,
     CellBottom,
+    /// no border selected yet
     Paragraph,
+    ParagraphTop,
+    ParagraphLeft,
+    ParagraphBottom,
+    ParagraphRight,
+    ParagraphBetween,
+    ParagraphBar,
+    ParagraphBox,
+    PageTop,
+    PageLeft,
+    PageBottom,
+    PageRight,
 }
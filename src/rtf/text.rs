@@ -4,6 +4,12 @@ use super::*;
 pub struct Page {
     pub sections: Vec<Section>,
 }
+impl Default for Page {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl Page {
     pub fn new() -> Page {
         Page {
@@ -14,11 +20,19 @@ impl Page {
 #[derive(Clone, Debug)]
 pub struct Section {
     pub paras: Vec<Paragraph>,
+    pub page_border: Option<PageBorder>,
 }
+impl Default for Section {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl Section {
     pub fn new() -> Section {
         Section {
             paras: vec![Paragraph::new()],
+            page_border: None,
         }
     }
 }
@@ -30,6 +44,12 @@ pub struct Paragraph {
     pub stylesheet: Option<i32>,
     pub style: Option<ParagraphStyle>,
 }
+impl Default for Paragraph {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl Paragraph {
     pub fn new() -> Paragraph {
         Paragraph {
@@ -40,24 +60,35 @@ impl Paragraph {
         }
     }
     pub fn is_empty(&self) -> bool {
-        self.lines.len() == 1 && self.lines[0].bytes.len() == 0
+        self.lines.len() == 1 && self.lines[0].text.is_empty()
     }
 }
 #[derive(Clone, Debug)]
 pub struct Line {
-    pub bytes: Vec<u8>,
+    /// decoded with the charset of `font` or the code page in effect
+    pub text: String,
     pub font: Option<i32>,
+    /// fonts for each kind of character, from `\af`
+    pub associated_fonts: AssociatedFonts,
+    /// `\csN` character style
+    pub char_style: Option<i32>,
     pub style: Option<FontStyle>,
-    pub encoding: Option<&'static encoding_rs::Encoding>,
     pub fit_text: Option<Twips>,
 }
+impl Default for Line {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl Line {
     pub fn new() -> Line {
         Line {
-            bytes: vec![],
+            text: String::new(),
             font: None,
+            associated_fonts: AssociatedFonts::default(),
+            char_style: None,
             style: None,
-            encoding: None,
             fit_text: None,
         }
     }
@@ -66,31 +97,27 @@ impl Line {
 #[derive(Clone, Debug)]
 pub struct Text {
     pub pages: Vec<Page>,
-    pub encoding: Option<&'static encoding_rs::Encoding>,
 }
-impl std::string::ToString for Text {
-    fn to_string(&self) -> String {
-        let bytes: Vec<u8> = self
-            .pages
+impl std::fmt::Display for Text {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        self.pages
             .iter()
             .flat_map(|page| page.sections.iter())
             .flat_map(|section| section.paras.iter())
             .flat_map(|paras| paras.lines.iter())
-            .flat_map(|line| line.bytes.iter())
-            .cloned()
-            .collect();
-        if let Some(decoder) = self.encoding {
-            decoder.decode(&bytes).0.into_owned()
-        } else {
-            encoding_rs::SHIFT_JIS.decode(&bytes).0.into_owned()
-        }
+            .try_for_each(|line| f.write_str(&line.text))
     }
 }
+impl Default for Text {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl Text {
     pub fn new() -> Text {
         Text {
             pages: vec![Page::new()],
-            encoding: Some(encoding_rs::SHIFT_JIS),
         }
     }
     pub fn last_page(&mut self) -> &mut Page {
@@ -108,11 +135,12 @@ impl Text {
             .paras
             .last_mut()
             .expect("must exist paragraph");
-        if lp.table.is_some() && follow_table {
-            let table = lp.table.as_mut().unwrap();
-            table.last_cell().paras.last_mut().expect("must exist para")
-        } else {
-            lp
+        if !follow_table {
+            return lp;
+        }
+        match lp.table {
+            Some(ref mut table) => table.last_cell().paras.last_mut().expect("must exist para"),
+            None => lp,
         }
     }
     pub fn last_line(&mut self) -> &mut Line {
@@ -144,43 +172,31 @@ impl Text {
             p.lines.push(Line::new());
         }
     }
-    pub fn decode_line(encoding: Option<&'static encoding_rs::Encoding>, line: &Line) -> String {
-        let bytes = &line.bytes;
-        if let Some(decoder) = encoding {
-            decoder.decode(&bytes).0.into_owned()
-        } else {
-            encoding_rs::SHIFT_JIS.decode(&bytes).0.into_owned()
-        }
-    }
     pub fn clear(&mut self) {
         self.pages = vec![Page::new()];
     }
 
-    pub fn last_or_new_line(
-        &mut self,
-        font: Option<i32>,
-        style: Option<FontStyle>,
-        encoding: Option<&'static encoding_rs::Encoding>,
-    ) -> &mut Line {
-        let (used, line_font, line_style, line_encoding) = {
+    pub fn last_or_new_line(&mut self, props: &CharacterProperties) -> &mut Line {
+        let font = props.font;
+        let style = props.font_style();
+        let (used, changed) = {
             let line = self.last_line();
 
             (
-                line.bytes.len() > 0,
-                line.font,
-                line.style.clone(),
-                line.encoding,
+                !line.text.is_empty(),
+                line.font != font
+                    || line.style != style
+                    || line.associated_fonts != props.associated_fonts
+                    || line.char_style != props.char_style,
             )
         };
-        if used
-            && (line_font != font
-                || line_style != style
-                || (line_encoding.is_some() && line_encoding != encoding))
-        {
+        if used && changed {
             self.new_line();
             let new_line = self.last_line();
             new_line.font = font;
             new_line.style = style;
+            new_line.associated_fonts = props.associated_fonts.clone();
+            new_line.char_style = props.char_style;
             new_line
         } else {
             let line = self.last_line();
@@ -190,23 +206,19 @@ impl Text {
             if line.style.is_none() {
                 line.style = style
             }
+            if !used {
+                line.associated_fonts = props.associated_fonts.clone();
+                line.char_style = props.char_style;
+            }
 
             line
         }
     }
     pub fn remove_unused(&mut self) {
-        if self.last_line().bytes.len() == 0 {
-            self.last_paragraph(false).lines.pop();
-        }
-        if self.last_paragraph(false).lines.len() == 0 && self.last_paragraph(false).table.is_none()
-        {
-            self.last_section().paras.pop();
-        }
-        if self.last_section().paras.len() == 0 {
-            self.last_page().sections.pop();
-        }
-        if self.last_page().sections.len() == 0 {
-            self.pages.pop();
+        // same paragraph as last_line(), and never leave it without lines
+        let para = self.last_paragraph(true);
+        if para.lines.len() > 1 && para.lines.last().is_some_and(|l| l.text.is_empty()) {
+            para.lines.pop();
         }
     }
     pub fn new_paragraph(&mut self, follow_table: bool) {
@@ -232,7 +244,6 @@ impl Text {
                     paragraph.stylesheet = parent.stylesheet;
                 }
                 table.last_cell().paras.push(paragraph);
-                println!("added paragraph {:?}", table.last_cell().paras);
             }
         } else {
             self.last_section().paras.push(Paragraph::new());
@@ -249,7 +260,7 @@ impl Text {
             let para = self.last_paragraph(in_table);
 
             (
-                para.lines.len() > 1 || !para.lines.last().unwrap().bytes.is_empty(),
+                para.lines.len() > 1 || para.lines.last().is_some_and(|l| !l.text.is_empty()),
                 para.style.clone(),
                 para.stylesheet,
                 had_table,
@@ -269,7 +280,7 @@ impl Text {
             pr
         } else if used && (para_style != style || para_stylesheet != stylesheet) {
             {
-                if self.last_line().bytes.is_empty() {
+                if self.last_line().text.is_empty() {
                     self.remove_unused();
                 }
             }
@@ -295,19 +306,7 @@ impl Text {
         let table = &mut self.last_paragraph(false).table;
         if let Some(table) = table {
             let last_row = table.last_row();
-            if last_row.border.is_some() {
-                Some(last_row.border.as_mut().unwrap())
-            } else {
-                last_row.border = Some(RowBorder {
-                    top: None,
-                    right: None,
-                    bottom: None,
-                    left: None,
-                    horizontal: None,
-                    vertical: None,
-                });
-                Some(last_row.border.as_mut().unwrap())
-            }
+            Some(last_row.border.get_or_insert_with(RowBorder::new))
         } else {
             None
         }
@@ -322,17 +321,7 @@ impl Text {
                 last_row.cell_opts.get_mut(last_row.cell_opt_pos)?
             };
 
-            if opts.border.is_some() {
-                Some(opts.border.as_mut().unwrap())
-            } else {
-                opts.border = Some(CellBorder {
-                    top: None,
-                    right: None,
-                    bottom: None,
-                    left: None,
-                });
-                Some(opts.border.as_mut().unwrap())
-            }
+            Some(opts.border.get_or_insert_with(CellBorder::new))
         } else {
             None
         }
@@ -429,6 +418,22 @@ impl Text {
                     None
                 }
             }
+            BorderSelect::PageTop
+            | BorderSelect::PageLeft
+            | BorderSelect::PageBottom
+            | BorderSelect::PageRight => {
+                let pb = self
+                    .last_section()
+                    .page_border
+                    .get_or_insert_with(PageBorder::default);
+                let side = match border_select {
+                    BorderSelect::PageTop => &mut pb.top,
+                    BorderSelect::PageLeft => &mut pb.left,
+                    BorderSelect::PageBottom => &mut pb.bottom,
+                    _ => &mut pb.right,
+                };
+                Some(side.get_or_insert_with(Border::new))
+            }
             _ => None,
         }
     }
@@ -447,11 +452,17 @@ impl Text {
     pub fn set_row_last(&mut self) {
         let table = &mut self.last_paragraph(false).table;
         if let Some(table) = table {
-            let mut last_row = table.last_row();
+            let last_row = table.last_row();
 
             last_row.is_last = true;
         }
     }
+    pub fn set_table_style(&mut self, number: i32) {
+        let table = &mut self.last_paragraph(false).table;
+        if let Some(table) = table {
+            table.last_row().style = Some(number);
+        }
+    }
     pub fn fit_text(&mut self, twips: i32) {
         let line = &mut self.last_line();
 
@@ -462,20 +473,20 @@ impl Text {
     pub fn set_cell_right(&mut self, right: Twips) {
         let table = &mut self.last_paragraph(false).table;
         if let Some(table) = table {
-            let mut last_row = table.last_row();
+            let last_row = table.last_row();
             if last_row.cell_opt_pos == 0 {
                 last_row.cells[0].opts.right = Some(right);
             } else if let Some(opt) = last_row.cell_opts.get_mut(last_row.cell_opt_pos) {
                 opt.right = Some(right);
             }
-            last_row.cell_opt_pos = last_row.cell_opt_pos + 1;
+            last_row.cell_opt_pos += 1;
             last_row.cell_opts.push(TableCellOption::new());
         }
     }
     pub fn set_cell_vert_align(&mut self, align: CellVerticalAlignment) {
         let table = &mut self.last_paragraph(false).table;
         if let Some(table) = table {
-            let mut last_row = table.last_row();
+            let last_row = table.last_row();
             if last_row.cell_opt_pos == 0 {
                 last_row.cells[0].opts.vert_align = align;
             } else if let Some(opt) = last_row.cell_opts.get_mut(last_row.cell_opt_pos) {
@@ -486,7 +497,7 @@ impl Text {
     pub fn set_cell_vert_merge_root(&mut self) {
         let table = &mut self.last_paragraph(false).table;
         if let Some(table) = table {
-            let mut last_row = table.last_row();
+            let last_row = table.last_row();
             if last_row.cell_opt_pos == 0 {
                 last_row.cells[0].opts.vert_merge_root = true;
             } else if let Some(opt) = last_row.cell_opts.get_mut(last_row.cell_opt_pos) {
@@ -497,7 +508,7 @@ impl Text {
     pub fn set_cell_vert_merged_cell(&mut self) {
         let table = &mut self.last_paragraph(false).table;
         if let Some(table) = table {
-            let mut last_row = table.last_row();
+            let last_row = table.last_row();
 
             if last_row.cell_opt_pos == 0 {
                 last_row.cells[0].opts.vert_merged_cell = true;
@@ -509,7 +520,7 @@ impl Text {
     pub fn set_cell_horiz_merge_root(&mut self) {
         let table = &mut self.last_paragraph(false).table;
         if let Some(table) = table {
-            let mut last_row = table.last_row();
+            let last_row = table.last_row();
             if last_row.cell_opt_pos == 0 {
                 last_row.cells[0].opts.horiz_merge_root = true;
             } else if let Some(opt) = last_row.cell_opts.get_mut(last_row.cell_opt_pos) {
@@ -520,7 +531,7 @@ impl Text {
     pub fn set_cell_horiz_merged_cell(&mut self) {
         let table = &mut self.last_paragraph(false).table;
         if let Some(table) = table {
-            let mut last_row = table.last_row();
+            let last_row = table.last_row();
 
             if last_row.cell_opt_pos == 0 {
                 last_row.cells[0].opts.horiz_merged_cell = true;
@@ -0,0 +1,124 @@
+use super::*;
+use std::io::Read;
+
+/// Bytes read from the input at a time
+const CHUNK_SIZE: usize = 64 * 1024;
+/// Bytes that must be buffered before a token is read, unless the input has ended,
+/// so that a control word is never cut off at the end of the buffer
+const LOOKAHEAD: usize = 256;
+
+/// Reads tokens from any input, holding only the bytes of the token being read
+pub(crate) struct Tokenizer<R: Read> {
+    reader: R,
+    buffer: Vec<u8>,
+    /// start of the unread part of `buffer`
+    pos: usize,
+    /// input offset of `buffer[0]`
+    offset: usize,
+    eof: bool,
+    failed: bool,
+    depth: usize,
+    control_word: Option<String>,
+}
+
+impl<R: Read> Tokenizer<R> {
+    pub fn new(reader: R) -> Self {
+        Self {
+            reader,
+            buffer: Vec::new(),
+            pos: 0,
+            offset: 0,
+            eof: false,
+            failed: false,
+            depth: 0,
+            control_word: None,
+        }
+    }
+
+    fn location(&self) -> Location {
+        Location {
+            offset: self.offset + self.pos,
+            depth: self.depth,
+            control_word: self.control_word.clone(),
+        }
+    }
+
+    /// Reads until `len` unread bytes are buffered or the input ends
+    fn fill(&mut self, len: usize) -> Result<(), Errors> {
+        if self.buffer.len() - self.pos >= len || self.eof {
+            return Ok(());
+        }
+        self.buffer.drain(..self.pos);
+        self.offset += self.pos;
+        self.pos = 0;
+        while self.buffer.len() < len && !self.eof {
+            let read = (&mut self.reader)
+                .take(CHUNK_SIZE as u64)
+                .read_to_end(&mut self.buffer)?;
+            self.eof = read < CHUNK_SIZE;
+        }
+        Ok(())
+    }
+
+    /// Length of the `\binN` token at the start of the unread bytes, header included
+    fn bin_len(&self) -> Option<usize> {
+        let rest = &self.buffer[self.pos..];
+        let digits = rest.strip_prefix(b"\\bin")?;
+        let count = digits.iter().take_while(|b| b.is_ascii_digit()).count();
+        let len: usize = std::str::from_utf8(&digits[..count]).ok()?.parse().ok()?;
+        let space = usize::from(digits.get(count) == Some(&b' '));
+        // a length past the end of memory is left to the tokenizer to reject
+        (4 + count + space).checked_add(len)
+    }
+
+    fn read(&mut self) -> Result<Option<(usize, Token)>, Errors> {
+        self.fill(LOOKAHEAD)?;
+        if let Some(len) = self.bin_len() {
+            // \bin data is only tokenized when all of it is buffered
+            self.fill(len)?;
+        }
+        let rest = &self.buffer[self.pos..];
+        if rest.is_empty() {
+            return Ok(None);
+        }
+        match read_token(CompleteByteSlice(rest)) {
+            // a token that consumes nothing means no rule matched the input
+            Ok((next, token)) if next.len() < rest.len() => {
+                let offset = self.offset + self.pos;
+                self.pos += rest.len() - next.len();
+                match &token {
+                    Token::StartGroup => self.depth += 1,
+                    Token::EndGroup => self.depth = std::cmp::max(self.depth, 1) - 1,
+                    Token::ControlWord { name, .. } if name != "'" => {
+                        self.control_word = Some(name.clone())
+                    }
+                    _ => {}
+                }
+                Ok(Some((offset, token)))
+            }
+            _ => {
+                let near = &rest[..std::cmp::min(rest.len(), 8)];
+                Err(Errors::ParseError {
+                    location: self.location(),
+                    message: format!(
+                        "unrecognized token near {:?}",
+                        String::from_utf8_lossy(near)
+                    ),
+                })
+            }
+        }
+    }
+}
+
+impl<R: Read> Iterator for Tokenizer<R> {
+    type Item = Result<(usize, Token), Errors>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed {
+            return None;
+        }
+        let token = self.read().transpose();
+        self.failed = matches!(token, Some(Err(_)));
+        token
+    }
+}
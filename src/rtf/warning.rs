@@ -0,0 +1,195 @@
+use super::*;
+use std::collections::BTreeMap;
+use std::fmt;
+
+/// Switches for [`Rtf::parse_with_options`]
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ParseOptions {
+    /// Fail on the first malformed construct (see [`WarningKind::is_error`])
+    /// instead of only recording it in [`Diagnostics`]
+    pub strict: bool,
+    /// Encoding for text in documents that set neither `\ansicpg` nor
+    /// `\deflang`/`\deflangfe`. windows-1252 when not given.
+    pub default_encoding: Option<&'static encoding_rs::Encoding>,
+    /// Keep the data of picture and object destinations (`\pict`, `\objdata`) in
+    /// [`Document::destinations`]. Off by default, so that large embedded pictures
+    /// are not held in memory; the dropped data is reported as
+    /// [`WarningKind::DroppedDestination`].
+    pub keep_binary_destinations: bool,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum WarningKind {
+    /// Control word that is neither known nor marked ignorable with `\*`
+    UnsupportedControlWord(String),
+    /// Control symbol that is not known
+    UnsupportedControlSymbol(char),
+    /// Text or control word outside of any destination (`{\rtf1 ...}` or a sub-destination)
+    ContentOutsideDocument,
+    /// `}` without a matching `{`
+    UnmatchedEndGroup,
+    /// Input ended while groups were still open
+    UnclosedGroup,
+    /// Ignorable destination (`{\*\name ...}`) that is not known; its content is kept
+    /// in [`Document::destinations`] but not rendered
+    UnsupportedDestination(String),
+    /// Codepage without a known encoding (`None` when no codepage was given at all),
+    /// and the encoding used instead
+    EncodingFallback {
+        codepage: Option<u16>,
+        used: &'static str,
+    },
+    /// `\bin` data of this many bytes was skipped
+    DroppedBinary(usize),
+    /// Text of this many bytes was written to a destination that cannot hold it
+    DroppedText(usize),
+    /// Data of a picture or object destination that was not kept
+    DroppedDestination { name: String, len: usize },
+}
+impl WarningKind {
+    /// Whether the document is malformed, as opposed to using something this crate
+    /// doesn't handle. Only these fail a strict parse.
+    pub fn is_error(&self) -> bool {
+        matches!(
+            self,
+            WarningKind::UnsupportedControlWord(_)
+                | WarningKind::UnsupportedControlSymbol(_)
+                | WarningKind::ContentOutsideDocument
+                | WarningKind::UnmatchedEndGroup
+                | WarningKind::UnclosedGroup
+        )
+    }
+}
+impl fmt::Display for WarningKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            WarningKind::UnsupportedControlWord(name) => {
+                write!(f, "unsupported control word \\{}", name)
+            }
+            WarningKind::UnsupportedControlSymbol(symbol) => {
+                write!(f, "unsupported control symbol \\{}", symbol)
+            }
+            WarningKind::ContentOutsideDocument => {
+                write!(f, "content found outside of any document group")
+            }
+            WarningKind::UnmatchedEndGroup => write!(f, "end group without a matching start group"),
+            WarningKind::UnclosedGroup => write!(f, "group is never closed"),
+            WarningKind::UnsupportedDestination(name) => {
+                write!(f, "unsupported destination \\{}", name)
+            }
+            WarningKind::EncodingFallback {
+                codepage: Some(codepage),
+                used,
+            } => write!(f, "no encoding for codepage {}, using {}", codepage, used),
+            WarningKind::EncodingFallback {
+                codepage: None,
+                used,
+            } => {
+                write!(f, "no codepage set, using {}", used)
+            }
+            WarningKind::DroppedBinary(len) => write!(f, "{} bytes of binary data dropped", len),
+            WarningKind::DroppedText(len) => write!(f, "{} bytes of text dropped", len),
+            WarningKind::DroppedDestination { name, len } => {
+                write!(f, "{} bytes of destination \\{} dropped", len, name)
+            }
+        }
+    }
+}
+
+/// Something noticed while processing, and where
+#[derive(Clone, Debug, PartialEq)]
+pub struct Warning {
+    pub location: Location,
+    pub kind: WarningKind,
+}
+impl fmt::Display for Warning {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} at {}", self.kind, self.location)
+    }
+}
+
+/// Everything noticed while parsing, returned with the [`Document`]
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Diagnostics {
+    /// All warnings in input order
+    pub warnings: Vec<Warning>,
+}
+impl Diagnostics {
+    pub fn is_empty(&self) -> bool {
+        self.warnings.is_empty()
+    }
+    /// Unsupported control words by name, with the location of each occurrence
+    pub fn unsupported_control_words(&self) -> BTreeMap<String, Vec<Location>> {
+        self.group_by(|kind| match kind {
+            WarningKind::UnsupportedControlWord(name) => Some(name.clone()),
+            WarningKind::UnsupportedControlSymbol(symbol) => Some(symbol.to_string()),
+            _ => None,
+        })
+    }
+    /// Unsupported destinations by name, with the location of each occurrence
+    pub fn unsupported_destinations(&self) -> BTreeMap<String, Vec<Location>> {
+        self.group_by(|kind| match kind {
+            WarningKind::UnsupportedDestination(name) => Some(name.clone()),
+            _ => None,
+        })
+    }
+    pub fn encoding_fallbacks(&self) -> impl Iterator<Item = &Warning> {
+        self.warnings
+            .iter()
+            .filter(|w| matches!(w.kind, WarningKind::EncodingFallback { .. }))
+    }
+    /// Content that is missing from the document: binary data, picture and object
+    /// data, text in a destination that cannot hold it, and text outside of any
+    /// destination
+    pub fn dropped_content(&self) -> impl Iterator<Item = &Warning> {
+        self.warnings.iter().filter(|w| {
+            matches!(
+                w.kind,
+                WarningKind::DroppedBinary(_)
+                    | WarningKind::DroppedText(_)
+                    | WarningKind::DroppedDestination { .. }
+                    | WarningKind::ContentOutsideDocument
+            )
+        })
+    }
+    fn group_by<F>(&self, key: F) -> BTreeMap<String, Vec<Location>>
+    where
+        F: Fn(&WarningKind) -> Option<String>,
+    {
+        let mut map = BTreeMap::<String, Vec<Location>>::new();
+        for warning in self.warnings.iter() {
+            if let Some(name) = key(&warning.kind) {
+                map.entry(name).or_default().push(warning.location.clone());
+            }
+        }
+        map
+    }
+}
+
+/// Collects warnings while processing; shared by the document and all group states
+#[derive(Clone, Debug, Default)]
+pub struct Reporter {
+    pub strict: bool,
+    pub location: Location,
+    pub diagnostics: Diagnostics,
+    pub error: Option<Warning>,
+}
+impl Reporter {
+    pub fn new(options: &ParseOptions) -> Self {
+        Self {
+            strict: options.strict,
+            ..Self::default()
+        }
+    }
+    pub fn report(&mut self, kind: WarningKind) {
+        debug!("{} at {}", kind, self.location);
+        let warning = Warning {
+            location: self.location.clone(),
+            kind,
+        };
+        if self.strict && warning.kind.is_error() && self.error.is_none() {
+            self.error = Some(warning.clone());
+        }
+        self.diagnostics.warnings.push(warning);
+    }
+}
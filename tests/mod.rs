@@ -8,4 +8,1098 @@ mod tests {
         let rtf = rtf_rs::Rtf::from_bytes(bytes).expect("must parse");
         assert_yaml_snapshot!(rtf.into_docx().unwrap());
     }
+    #[test]
+    fn rtf_parse_document() {
+        let bytes = include_bytes!("./mocks/helloworld.rtf");
+        let rtf = rtf_rs::Rtf::from_bytes(bytes).expect("must parse");
+        let doc = rtf.parse();
+        assert_eq!(doc.info.author.as_deref(), Some("yun tara"));
+        assert!(doc.body.is_some());
+        assert!(!doc.fonts.is_empty());
+    }
+    #[test]
+    fn rtf_parse_error_location() {
+        let err = rtf_rs::Rtf::from_bytes(br"{\rtf1{\fonttbl\f0 a\'zz}}")
+            .err()
+            .expect("must fail");
+        match &err {
+            rtf_rs::Errors::ParseError { location, .. } => {
+                assert_eq!(location.offset, 20);
+                assert_eq!(location.depth, 2);
+                assert_eq!(location.control_word.as_deref(), Some("f"));
+            }
+            _ => panic!("unexpected error {:?}", err),
+        }
+        assert!(err.to_string().contains("byte 20"));
+    }
+    /// Hands out the input a few bytes at a time, then fails if asked to
+    struct TrickleReader<'a> {
+        data: &'a [u8],
+        step: usize,
+        fail_at_end: bool,
+    }
+    impl std::io::Read for TrickleReader<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            if self.data.is_empty() && self.fail_at_end {
+                return Err(std::io::Error::other("connection reset"));
+            }
+            self.step = self.step % 7 + 1;
+            let len = self.step.min(buf.len()).min(self.data.len());
+            buf[..len].copy_from_slice(&self.data[..len]);
+            self.data = &self.data[len..];
+            Ok(len)
+        }
+    }
+    #[test]
+    fn rtf_from_reader() {
+        let bytes = include_bytes!("./mocks/helloworld.rtf");
+        let expected = rtf_rs::Rtf::from_bytes(bytes).expect("must parse").parse();
+        let reader = TrickleReader {
+            data: bytes,
+            step: 0,
+            fail_at_end: false,
+        };
+        let doc = rtf_rs::Rtf::from_reader(reader).expect("must parse");
+        assert_eq!(doc.to_text(), expected.to_text());
+        assert_eq!(doc.diagnostics, expected.diagnostics);
+        assert_eq!(docx_document_xml(&doc), docx_document_xml(&expected));
+
+        // \bin data and error offsets across reads
+        let mut bytes = br"{\rtf1 a{\*\pict\bin300 ".to_vec();
+        bytes.extend([b'}'; 300]);
+        bytes.extend(br"}b\'zz}");
+        let reader = TrickleReader {
+            data: &bytes,
+            step: 0,
+            fail_at_end: false,
+        };
+        match rtf_rs::Rtf::from_reader(reader) {
+            Err(rtf_rs::Errors::ParseError { location, .. }) => {
+                assert_eq!(location.offset, bytes.len() - 5);
+                assert_eq!(location.depth, 1);
+                assert_eq!(location.control_word.as_deref(), Some("pict"));
+            }
+            other => panic!("unexpected result {:?}", other.map(|doc| doc.to_text())),
+        }
+        let reader = TrickleReader {
+            data: br"{\rtf1 abc",
+            step: 0,
+            fail_at_end: true,
+        };
+        assert!(matches!(
+            rtf_rs::Rtf::from_reader(reader),
+            Err(rtf_rs::Errors::IoError(_))
+        ));
+    }
+    /// Counts the bytes the current thread holds, to see what parsing keeps in memory
+    struct CountingAllocator;
+    thread_local! {
+        static HELD: std::cell::Cell<isize> = const { std::cell::Cell::new(0) };
+        static PEAK: std::cell::Cell<isize> = const { std::cell::Cell::new(0) };
+    }
+    fn count_allocation(change: isize) {
+        let _ = HELD.try_with(|held| {
+            held.set(held.get() + change);
+            let _ = PEAK.try_with(|peak| peak.set(peak.get().max(held.get())));
+        });
+    }
+    unsafe impl std::alloc::GlobalAlloc for CountingAllocator {
+        unsafe fn alloc(&self, layout: std::alloc::Layout) -> *mut u8 {
+            count_allocation(layout.size() as isize);
+            std::alloc::System.alloc(layout)
+        }
+        unsafe fn dealloc(&self, ptr: *mut u8, layout: std::alloc::Layout) {
+            count_allocation(-(layout.size() as isize));
+            std::alloc::System.dealloc(ptr, layout)
+        }
+    }
+    #[global_allocator]
+    static ALLOCATOR: CountingAllocator = CountingAllocator;
+    #[test]
+    fn rtf_binary_destinations_are_streamed() {
+        use std::io::Read;
+        let pict_len = 4 << 20;
+        let reader = (&br"{\rtf1 a{\*\shppict{\pict\pngblip "[..])
+            .chain(std::io::repeat(b'f').take(pict_len))
+            .chain(&br"}}b}"[..]);
+        let held = HELD.with(|held| held.get());
+        PEAK.with(|peak| peak.set(held));
+        let doc = rtf_rs::Rtf::from_reader(reader).expect("must parse");
+        let peak = PEAK.with(|peak| peak.get()) - held;
+        assert_eq!(doc.to_text(), "ab");
+        // the tokenizer's read buffer and one token, not the picture
+        assert!(peak < 1 << 20, "held {} bytes while parsing", peak);
+    }
+    #[test]
+    fn rtf_binary_destinations_are_dropped() {
+        let mut bytes = br"{\rtf1 a{\*\shppict{\pict\pngblip ".to_vec();
+        bytes.resize(bytes.len() + 1_000_000, b'f');
+        bytes.extend(br"}}b{\object{\*\objdata 0102}}c}");
+        let doc = rtf_rs::Rtf::from_reader(&bytes[..]).expect("must parse");
+        assert_eq!(doc.to_text(), "abc");
+        assert!(!doc.destinations.contains_key("pict"));
+        assert!(!doc.destinations.contains_key("objdata"));
+        let dropped: Vec<_> = doc
+            .diagnostics
+            .dropped_content()
+            .map(|w| w.kind.clone())
+            .collect();
+        assert_eq!(
+            dropped,
+            vec![
+                rtf_rs::WarningKind::DroppedDestination {
+                    name: "pict".to_string(),
+                    len: 1_000_000,
+                },
+                rtf_rs::WarningKind::DroppedDestination {
+                    name: "objdata".to_string(),
+                    len: 4,
+                },
+            ]
+        );
+
+        let options = rtf_rs::ParseOptions {
+            keep_binary_destinations: true,
+            ..Default::default()
+        };
+        let doc = rtf_rs::Rtf::from_reader_with_options(&bytes[..], &options).expect("must parse");
+        assert_eq!(doc.destinations["pict"].as_bytes().len(), 1_000_000);
+        assert_eq!(doc.destinations["objdata"].as_bytes(), b"0102");
+        assert_eq!(doc.diagnostics.dropped_content().count(), 0);
+    }
+    #[test]
+    fn rtf_strict_and_lenient() {
+        let rtf = rtf_rs::Rtf::from_bytes(br"{\rtf1\ansi\foo a}}").expect("must parse");
+        let doc = rtf.parse();
+        let kinds: Vec<_> = doc
+            .diagnostics
+            .warnings
+            .iter()
+            .map(|w| w.kind.clone())
+            .collect();
+        assert_eq!(
+            kinds,
+            vec![
+                rtf_rs::WarningKind::UnsupportedControlWord("foo".to_string()),
+                rtf_rs::WarningKind::UnmatchedEndGroup,
+            ]
+        );
+        assert_eq!(doc.diagnostics.warnings[0].location.offset, 11);
+        assert_eq!(doc.diagnostics.warnings[0].location.depth, 1);
+        assert_eq!(doc.to_text(), "a");
+
+        let strict = rtf_rs::ParseOptions {
+            strict: true,
+            ..Default::default()
+        };
+        match rtf.parse_with_options(&strict) {
+            Err(rtf_rs::Errors::ValidationError(warning)) => {
+                assert_eq!(warning, doc.diagnostics.warnings[0]);
+            }
+            other => panic!("unexpected result {:?}", other.map(|d| d.diagnostics)),
+        }
+        let rtf = rtf_rs::Rtf::from_bytes(br"{\rtf1\ansi a").expect("must parse");
+        assert!(rtf.parse_with_options(&strict).is_err());
+        let bytes = include_bytes!("./mocks/helloworld.rtf");
+        let rtf = rtf_rs::Rtf::from_bytes(bytes).expect("must parse");
+        assert!(rtf.parse_with_options(&strict).is_ok());
+    }
+    #[test]
+    fn rtf_diagnostics() {
+        let rtf = rtf_rs::Rtf::from_bytes(
+            br"{\rtf1\ansi\ansicpg99 a\foo\foo{\*\bar hidden}\bin2 xyb{\*\bar}}",
+        )
+        .expect("must parse");
+        let doc = rtf.parse();
+        let diagnostics = &doc.diagnostics;
+        let words = diagnostics.unsupported_control_words();
+        assert_eq!(words.len(), 1);
+        assert_eq!(words["foo"].len(), 2);
+        assert_eq!(words["foo"][0].offset, 23);
+        let destinations = diagnostics.unsupported_destinations();
+        assert_eq!(destinations["bar"].len(), 2);
+        let fallback: Vec<_> = diagnostics.encoding_fallbacks().collect();
+        assert_eq!(fallback.len(), 1);
+        assert_eq!(
+            fallback[0].kind,
+            rtf_rs::WarningKind::EncodingFallback {
+                codepage: Some(99),
+                used: "windows-1252"
+            }
+        );
+        let dropped: Vec<_> = diagnostics.dropped_content().collect();
+        assert_eq!(dropped.len(), 1);
+        assert_eq!(dropped[0].kind, rtf_rs::WarningKind::DroppedBinary(2));
+        assert_eq!(doc.to_text(), "ab");
+
+        // content of the wrong kind is refused, so the caller can report it
+        let mut dest = rtf_rs::Destination::Text(rtf_rs::Text::new());
+        assert!(!dest.append_bytes(b"x"));
+        assert!(dest.append_bytes(b""));
+        let mut dest = rtf_rs::Destination::Bytes(vec![]);
+        assert!(dest.append_bytes(b"x"));
+        assert_eq!(dest.as_bytes(), b"x");
+    }
+    fn docx_document_xml(doc: &rtf_rs::Document) -> String {
+        String::from_utf8(doc.build_xml().document).expect("must be utf-8")
+    }
+    #[test]
+    fn rtf_font_charsets() {
+        let rtf = rtf_rs::Rtf::from_bytes(
+            br"{\rtf1\ansi\ansicpg1252{\fonttbl{\f0\fcharset204 \'cf\'f0\'e8\'ec\'e5\'f0;}{\f1\fcharset134 \'cb\'ce\'cc\'e5;}{\f2\fcharset177 \'f9\'ec\'e5\'ed;}}
+\f0 \'cf\'f0\'e8\'e2\'e5\'f2 \f1 \'d6\'d0\'ce\'c4 \f2 \'f9\'ec\'e5\'ed}",
+        )
+        .expect("must parse");
+        let doc = rtf.parse();
+        assert_eq!(doc.fonts[&0].font_name, "Пример");
+        assert_eq!(doc.fonts[&0].charset, Some(rtf_rs::Charset::Cyrillic));
+        assert_eq!(doc.fonts[&1].font_name, "宋体");
+        assert_eq!(doc.fonts[&2].font_name, "שלום");
+        let xml = docx_document_xml(&doc);
+        assert!(xml.contains("Привет"));
+        assert!(xml.contains("中文"));
+        assert!(xml.contains("שלום"));
+        assert_eq!(rtf_rs::Charset::from(130).codepage(), Some(1361));
+        assert_eq!(rtf_rs::Charset::from(130).encoding(), None);
+        assert_eq!(
+            rtf_rs::Charset::from(129).encoding(),
+            Some(encoding_rs::EUC_KR)
+        );
+    }
+    #[test]
+    fn rtf_split_dbcs_characters() {
+        // lead bytes as \'xx with literal trail bytes, as Word writes them
+        let rtf = rtf_rs::Rtf::from_bytes(br"{\rtf1\ansi\ansicpg932 \'83e\'83X\'83g}")
+            .expect("must parse");
+        assert_eq!(rtf.parse().to_text(), "テスト");
+        let rtf = rtf_rs::Rtf::from_bytes(
+            br"{\rtf1\ansi{\fonttbl{\f0\fcharset128 MS Mincho;}}\f0 \'83e\'83X\'83g\'81f\'81h}",
+        )
+        .expect("must parse");
+        assert_eq!(rtf.parse().to_text(), "テスト’”");
+    }
+    #[test]
+    fn rtf_unicode_text() {
+        let rtf = rtf_rs::Rtf::from_bytes(
+            br"{\rtf1\ansi\ansicpg1252\uc1{\fonttbl{\f0\fcharset0 Arial;}{\f1\fcharset204 Arial Cyr;}}{\info{\author \'e9\u8364?}}
+\f0 caf\'e9 {\f1 \'cf\'f0\'e8} \u8212? \endash\ldblquote x\rdblquote}",
+        )
+        .expect("must parse");
+        let doc = rtf.parse();
+        assert_eq!(doc.info.author.as_deref(), Some("é€"));
+        assert_eq!(doc.to_text(), "café При \u{2014} \u{2013}\u{201c}x\u{201d}");
+        let body = doc.body.as_ref().expect("must have a body");
+        let lines: Vec<_> = body.pages[0].sections[0].paras[0]
+            .lines
+            .iter()
+            .map(|line| (line.font, line.text.as_str()))
+            .collect();
+        assert_eq!(lines[1], (Some(1), "При"));
+    }
+    #[test]
+    fn rtf_unicode_escapes() {
+        let text = |bytes: &[u8]| {
+            rtf_rs::Rtf::from_bytes(bytes)
+                .expect("must parse")
+                .parse()
+                .to_text()
+        };
+        // negative values and surrogate pairs
+        assert_eq!(text(br"{\rtf1 \u-10179?\u-8704?}"), "\u{1f600}");
+        assert_eq!(text(br"{\rtf1 \u-10179?x}"), "\u{fffd}x");
+        // \uc is scoped to its group, \'xx and control symbols are one character each
+        assert_eq!(
+            text(br"{\rtf1 {\uc2\u26085\'93\'fa}\u8364\'80\u160\~\u8212\emdash!}"),
+            "\u{65e5}\u{20ac}\u{a0}\u{2014}!"
+        );
+        assert_eq!(text(br"{\rtf1\uc0\u233 x}"), "\u{e9}x");
+    }
+    #[test]
+    fn rtf_character_properties() {
+        let bytes = br"{\rtf1{\fonttbl{\f0 A;}{\f1 B;}}\b a{\i\f1 b}c\b0 d\ul1 e{\ul0 f}g\plain h\i\pard i}";
+        let doc = rtf_rs::Rtf::from_bytes(bytes).expect("must parse").parse();
+        let body = doc.body.as_ref().expect("must have a body");
+        let lines: Vec<_> = body
+            .pages
+            .iter()
+            .flat_map(|page| page.sections.iter())
+            .flat_map(|section| section.paras.iter())
+            .flat_map(|para| para.lines.iter())
+            .map(|line| {
+                let style = line.style.clone().unwrap_or_default();
+                (
+                    line.text.as_str(),
+                    line.font,
+                    style.bold == Some(true),
+                    style.italic == Some(true),
+                    style.underline.unwrap_or_default() != rtf_rs::UnderlineType::None,
+                )
+            })
+            .collect();
+        assert_eq!(
+            lines,
+            vec![
+                ("a", None, true, false, false),
+                ("b", Some(1), true, true, false),
+                ("c", None, true, false, false),
+                ("d", None, false, false, false),
+                ("e", None, false, false, true),
+                ("f", None, false, false, false),
+                ("g", None, false, false, true),
+                ("h", None, false, false, false),
+                ("i", None, false, true, false),
+            ]
+        );
+    }
+    #[test]
+    fn rtf_character_effects() {
+        let bytes = br"{\rtf1 \strike a\strike0 {\super 1}{\sub 2}{\up6 3}{\caps\v x}{\scaps\outl\shad\embo\impr y}\striked1 z}";
+        let doc = rtf_rs::Rtf::from_bytes(bytes).expect("must parse").parse();
+        let body = doc.body.as_ref().expect("must have a body");
+        let styles: Vec<_> = body.pages[0].sections[0].paras[0]
+            .lines
+            .iter()
+            .map(|line| line.style.clone().unwrap_or_default())
+            .collect();
+        assert_eq!(styles[0].strike, Some(true));
+        assert_eq!(styles[1].superscript, Some(true));
+        assert_eq!(styles[1].strike, Some(false));
+        assert_eq!(styles[2].subscript, Some(true));
+        assert_eq!(styles[3].offset, Some(6));
+        assert_eq!((styles[4].caps, styles[4].hidden), (Some(true), Some(true)));
+        let y = &styles[5];
+        assert_eq!(
+            [y.small_caps, y.outline, y.shadow, y.emboss, y.imprint],
+            [Some(true); 5]
+        );
+        assert_eq!(y.caps, None);
+        assert_eq!(styles[6].double_strike, Some(true));
+
+        let xml = docx_document_xml(&doc);
+        assert!(xml.contains("<w:strike />"));
+        assert!(xml.contains("<w:dstrike />"));
+        assert_eq!(
+            xml.matches(r#"<w:vertAlign w:val="superscript" />"#)
+                .count(),
+            1
+        );
+        assert!(xml.contains(r#"<w:vertAlign w:val="subscript" />"#));
+        assert!(xml.contains(r#"<w:position w:val="6" />"#));
+        assert_eq!(xml.matches(r#"<w:caps w:val="true" />"#).count(), 1);
+        assert!(xml.contains("<w:vanish />"));
+        assert!(xml.contains(
+            r#"<w:smallCaps /><w:strike w:val="false" /><w:outline /><w:shadow /><w:emboss /><w:imprint /></w:rPr>"#
+        ));
+        assert!(!xml.contains("w:fitText"));
+        let styles = rtf_rs::Rtf::from_bytes(br"{\rtf1{\stylesheet{\dn4\outl Normal;}}x}")
+            .expect("must parse")
+            .parse()
+            .build_xml()
+            .styles;
+        let styles = String::from_utf8(styles).expect("must be utf-8");
+        assert!(styles.contains(r#"<w:outline /><w:position w:val="-4" />"#));
+
+        // a negative size leaves the size as it was
+        let doc = rtf_rs::Rtf::from_bytes(br"{\rtf1\fs20 {\fs-4 x}}")
+            .expect("must parse")
+            .parse();
+        let x =
+            &doc.body.as_ref().expect("must have a body").pages[0].sections[0].paras[0].lines[0];
+        assert_eq!(x.style.as_ref().and_then(|style| style.size), Some(20));
+        assert!(docx_document_xml(&doc).contains(r#"<w:sz w:val="20" />"#));
+
+        // turned off is not the same as never set
+        let doc = rtf_rs::Rtf::from_bytes(br"{\rtf1 a\i1\i0\ulnone b}")
+            .expect("must parse")
+            .parse();
+        let lines =
+            &doc.body.as_ref().expect("must have a body").pages[0].sections[0].paras[0].lines;
+        assert_eq!(lines[0].style, None);
+        let b = lines[1].style.clone().expect("must have a style");
+        assert_eq!((b.italic, b.bold), (Some(false), None));
+        assert_eq!(b.underline, Some(rtf_rs::UnderlineType::None));
+        // so are colors and offsets set to 0
+        let doc = rtf_rs::Rtf::from_bytes(br"{\rtf1 {\cf0\cb0\ulc0\up0 a}}")
+            .expect("must parse")
+            .parse();
+        let a = doc.body.as_ref().expect("must have a body").pages[0].sections[0].paras[0].lines[0]
+            .style
+            .clone()
+            .expect("must have a style");
+        assert_eq!(
+            (a.foreground_color, a.background_color, a.underline_color),
+            (Some(0), Some(0), Some(0))
+        );
+        assert_eq!(a.offset, Some(0));
+    }
+    #[test]
+    fn rtf_underline_styles() {
+        use rtf_rs::UnderlineType;
+        let bytes = br"{\rtf1{\colortbl;\red255\green0\blue0;}\ul a\uldb b\ulwave\ulc1 c\ulnone d\ulthdash e\uld0 f{\ululdbwave g}\ulw h}";
+        let doc = rtf_rs::Rtf::from_bytes(bytes).expect("must parse").parse();
+        let body = doc.body.as_ref().expect("must have a body");
+        let styles: Vec<_> = body.pages[0].sections[0].paras[0]
+            .lines
+            .iter()
+            .map(|line| line.style.clone().unwrap_or_default())
+            .collect();
+        let underlines: Vec<_> = styles.iter().map(|style| style.underline).collect();
+        assert_eq!(
+            underlines,
+            vec![
+                Some(UnderlineType::Single),
+                Some(UnderlineType::Double),
+                Some(UnderlineType::Wave),
+                Some(UnderlineType::None),
+                Some(UnderlineType::ThickDash),
+                Some(UnderlineType::None),
+                Some(UnderlineType::DoubleWave),
+                Some(UnderlineType::Words),
+            ]
+        );
+        assert_eq!(styles[2].underline_color, Some(1));
+        let red = doc
+            .color(styles[2].underline_color.unwrap_or(0))
+            .expect("must have the color");
+        assert_eq!((red.r, red.g, red.b), (255, 0, 0));
+        assert_eq!(doc.color(0), None);
+
+        let xml = docx_document_xml(&doc);
+        for val in ["single", "double"] {
+            assert!(
+                xml.contains(&format!(r#"<w:u w:val="{}" />"#, val)),
+                "{}",
+                val
+            );
+        }
+        // \ulc1 holds from c on
+        for val in ["wave", "none", "dashedHeavy", "wavyDouble", "words"] {
+            assert!(
+                xml.contains(&format!(r#"<w:u w:val="{}" w:color="ff0000" />"#, val)),
+                "{}",
+                val
+            );
+        }
+    }
+    #[test]
+    fn rtf_associated_fonts() {
+        let bytes = br"{\rtf1\ansi{\fonttbl{\f0\fcharset0 Century;}{\f1\fcharset0 Arial;}{\f43\fcharset128 MS Mincho;}}
+\loch\af0\hich\af0\dbch\af43\loch\f0 abc{\rtlch\af1\ltrch\dbch \'82\'a0}}";
+        let doc = rtf_rs::Rtf::from_bytes(bytes).expect("must parse").parse();
+        let body = doc.body.as_ref().expect("must have a body");
+        let lines = &body.pages[0].sections[0].paras[0].lines;
+        assert_eq!(lines[0].text, "abc");
+        assert_eq!(lines[0].font, Some(0));
+        assert_eq!(
+            lines[0].associated_fonts,
+            rtf_rs::AssociatedFonts {
+                ascii: Some(0),
+                high_ansi: Some(0),
+                east_asia: Some(43),
+                complex: None,
+            }
+        );
+        // \dbch text is decoded with the East Asian font's charset
+        assert_eq!(lines[1].text, "あ");
+        assert_eq!(lines[1].associated_fonts.complex, Some(1));
+
+        let xml = docx_document_xml(&doc);
+        assert!(xml.contains(r#"w:ascii="Century""#));
+        assert!(xml.contains(r#"w:hAnsi="Century""#));
+        assert!(xml.contains(r#"w:eastAsia="MS Mincho""#));
+        assert!(xml.contains(r#"w:cs="Arial""#));
+    }
+    #[test]
+    fn rtf_font_metadata() {
+        let bytes = br"{\rtf1{\fonttbl{\f0\froman\fcharset0\fprq2{\*\panose 02020603050405020304}Times New Roman{\*\falt Times};}
+{\f1\fbimajor\fswiss\fcharset128\fprq1\fbias1 MS Gothic;}}x}";
+        let doc = rtf_rs::Rtf::from_bytes(bytes).expect("must parse").parse();
+        let times = &doc.fonts[&0];
+        assert_eq!(times.font_name, "Times New Roman");
+        assert_eq!(times.alt_font_name.as_deref(), Some("Times"));
+        assert_eq!(times.panose.as_deref(), Some("02020603050405020304"));
+        assert_eq!(times.pitch, Some(2));
+        assert_eq!(times.theme, None);
+        let gothic = &doc.fonts[&1];
+        assert_eq!(gothic.font_name, "MS Gothic");
+        assert_eq!(gothic.alt_font_name, None);
+        assert_eq!(gothic.panose, None);
+        assert_eq!(gothic.pitch, Some(1));
+        assert_eq!(gothic.bias, Some(1));
+        assert_eq!(gothic.theme, Some(rtf_rs::ThemeFont::MajorBidi));
+        assert!(doc.destinations.is_empty());
+
+        let font_table = String::from_utf8(doc.build_xml().font_table).expect("must be utf-8");
+        assert!(font_table.contains(
+            r#"<w:font w:name="Times New Roman"><w:altName w:val="Times" /><w:panose1 w:val="02020603050405020304" /><w:charset w:val="00" /><w:family w:val="roman" /><w:pitch w:val="variable" /></w:font>"#
+        ));
+        assert!(font_table.contains(
+            r#"<w:font w:name="MS Gothic"><w:charset w:val="80" /><w:family w:val="swiss" /><w:pitch w:val="fixed" /></w:font>"#
+        ));
+    }
+    #[test]
+    fn rtf_docx_styles() {
+        let bytes =
+            br"{\rtf1{\stylesheet{\qj Normal;}{\s1\sbasedon0\snext0\sqformat\qc\b\fs32 heading 1;}
+{\*\cs10 Default Paragraph Font;}{\*\cs15\sbasedon10\i Emphasis;}{\*\ts11\tsrowd Normal Table;}}
+\pard\plain\s1\qc\b\fs32 Title\par\pard\plain Body {\cs15\i text}\par}";
+        let doc = rtf_rs::Rtf::from_bytes(bytes).expect("must parse").parse();
+        let heading = &doc.stylesheets[&1];
+        assert_eq!(heading.name, "heading 1");
+        assert_eq!(heading.kind, rtf_rs::StyleKind::Paragraph);
+        assert_eq!((heading.based_on, heading.next), (Some(0), Some(0)));
+        assert_eq!(doc.stylesheets[&15].kind, rtf_rs::StyleKind::Character);
+        assert_eq!(doc.stylesheets[&11].kind, rtf_rs::StyleKind::Table);
+
+        let xml = doc.build_xml();
+        let styles = String::from_utf8(xml.styles).expect("must be utf-8");
+        assert_eq!(styles.matches(r#"w:styleId="Normal""#).count(), 1);
+        assert!(styles.contains(
+            r#"<w:style w:type="paragraph" w:styleId="heading1"><w:name w:val="heading 1" /><w:rPr><w:sz w:val="32" /><w:szCs w:val="32" /><w:b /><w:bCs /></w:rPr><w:pPr><w:rPr /><w:jc w:val="center" /></w:pPr><w:next w:val="Normal" /><w:qFormat /><w:basedOn w:val="Normal" /></w:style>"#
+        ));
+        assert!(styles.contains(
+            r#"<w:style w:type="character" w:styleId="Emphasis"><w:name w:val="Emphasis" />"#
+        ));
+        assert!(styles.contains(r#"<w:basedOn w:val="DefaultParagraphFont" />"#));
+        assert!(styles.contains(r#"<w:style w:type="table" w:styleId="NormalTable">"#));
+        let document = String::from_utf8(xml.document).expect("must be utf-8");
+        assert!(document.contains(r#"<w:pStyle w:val="heading1" />"#));
+        assert!(document.contains(r#"<w:rStyle w:val="Emphasis" />"#));
+    }
+    #[test]
+    fn rtf_style_inheritance() {
+        let bytes =
+            br"{\rtf1{\fonttbl{\f0 Times;}{\f1 Arial;}}{\stylesheet{\ql\f1\fs20\sqformat Normal;}
+{\s1\sbasedon0\snext0\slink15\li720\b heading 1;}{\s2\sbasedon1\qc\i heading 2;}
+{\*\cs15\additive\sbasedon10\slink1\ssemihidden\b heading 1 Char;}{\*\ts11\tsrowd Normal Table;}}
+\pard\plain\s2 Title {\cs15 char}\par
+\trowd\ts11\cellx1000\pard\plain\intbl cell\cell\row}";
+        let doc = rtf_rs::Rtf::from_bytes(bytes).expect("must parse").parse();
+        let stylesheets = &doc.stylesheets;
+        let heading2 = &stylesheets[&2];
+        let chain: Vec<_> = heading2
+            .chain(stylesheets)
+            .iter()
+            .map(|s| s.number)
+            .collect();
+        assert_eq!(chain, vec![2, 1, 0]);
+        let font_style = heading2
+            .resolved_font_style(stylesheets)
+            .expect("must inherit");
+        assert_eq!(
+            (font_style.bold, font_style.italic),
+            (Some(true), Some(true))
+        );
+        assert_eq!(font_style.size, Some(20));
+        let para_style = heading2
+            .resolved_para_style(stylesheets)
+            .expect("must inherit");
+        assert_eq!(para_style.align, Some(rtf_rs::Align::Center));
+        assert_eq!(para_style.left_indent, Some(720));
+        assert_eq!(heading2.resolved_font(stylesheets), Some(1));
+
+        let heading1 = &stylesheets[&1];
+        assert_eq!(heading1.link, Some(15));
+        assert!(!heading1.q_format);
+        assert!(stylesheets[&0].q_format);
+        let char_style = &stylesheets[&15];
+        assert_eq!(char_style.name, "heading 1 Char");
+        assert!(char_style.additive && char_style.semi_hidden);
+        assert_eq!(char_style.link, Some(1));
+
+        let lines: Vec<_> = doc.body.as_ref().expect("must have body").pages[0].sections[0]
+            .paras
+            .iter()
+            .flat_map(|para| para.lines.iter())
+            .filter(|line| line.text == "char")
+            .collect();
+        assert_eq!(lines[0].char_style, Some(15));
+
+        let xml = doc.build_xml();
+        let styles = String::from_utf8(xml.styles).expect("must be utf-8");
+        assert!(styles.contains(r#"<w:link w:val="heading1Char" />"#));
+        assert!(styles.contains(r#"<w:semiHidden />"#));
+        let document = String::from_utf8(xml.document).expect("must be utf-8");
+        assert!(document.contains(r#"<w:tblStyle w:val="NormalTable" />"#));
+    }
+    #[test]
+    fn rtf_run_format() {
+        let bytes = br"{\rtf1\deff0{\fonttbl{\f0 Times;}{\f1 Arial;}}{\stylesheet{\ql\fs20 Normal;}
+{\s1\sbasedon0\qc\f1\b\fs32 heading 1;}{\*\cs10\additive Default Paragraph Font;}
+{\*\cs15\sbasedon10\ul Emphasis;}}
+\pard\plain\s1\li360 Title {\i more}\par\pard\plain Body {\cs15 text}\par}";
+        let doc = rtf_rs::Rtf::from_bytes(bytes).expect("must parse").parse();
+        let paras = &doc.body.as_ref().expect("must have body").pages[0].sections[0].paras;
+        let line = |para: &rtf_rs::Paragraph, text: &str| {
+            para.lines
+                .iter()
+                .find(|line| line.text == text)
+                .cloned()
+                .expect("must have line")
+        };
+
+        let heading = &paras[0];
+        let format = doc.paragraph_format(heading);
+        assert_eq!(format.align, Some(rtf_rs::Align::Center));
+        assert_eq!(format.left_indent, Some(360));
+        let title = doc.run_format(heading, &line(heading, "Title "));
+        assert_eq!(title.font, Some(1));
+        assert_eq!((title.style.bold, title.style.italic), (Some(true), None));
+        assert_eq!(title.style.size, Some(32));
+        let more = doc.run_format(heading, &line(heading, "more"));
+        assert_eq!(
+            (more.style.bold, more.style.italic),
+            (Some(true), Some(true))
+        );
+
+        let body = &paras[1];
+        assert_eq!(doc.paragraph_format(body).align, Some(rtf_rs::Align::Left));
+        let plain = doc.run_format(body, &line(body, "Body "));
+        assert_eq!(plain.font, Some(0));
+        assert_eq!(plain.style.bold, None);
+        assert_eq!(plain.style.size, Some(20));
+        let text = doc.run_format(body, &line(body, "text"));
+        assert_eq!(text.style.underline, Some(rtf_rs::UnderlineType::Single));
+        assert_eq!(text.style.size, Some(20));
+
+        // direct formatting turns off what the styles turn on
+        let bytes = br"{\rtf1{\stylesheet{\b\i Normal;}{\*\cs10\ul Strong;}}
+\pard\plain\s0 {\cs10\b0\i0\ulnone off}\par}";
+        let doc = rtf_rs::Rtf::from_bytes(bytes).expect("must parse").parse();
+        let para = &doc.body.as_ref().expect("must have body").pages[0].sections[0].paras[0];
+        let off = doc.run_format(para, &line(para, "off"));
+        assert_eq!(off.style.bold, Some(false));
+        assert_eq!(off.style.italic, Some(false));
+        assert_eq!(off.style.underline, Some(rtf_rs::UnderlineType::None));
+        let xml = docx_document_xml(&doc);
+        assert!(xml.contains(r#"<w:b w:val="false" />"#));
+        assert!(xml.contains(r#"<w:i w:val="false" />"#));
+        assert!(xml.contains(r#"<w:u w:val="none" />"#));
+
+        // and so do colors and offsets set to 0
+        let bytes = br"{\rtf1{\colortbl;\red255\green0\blue0;}{\stylesheet{\cf1\up6 Normal;}}
+\pard\plain\s0 {\cf0\up0 zero}\par}";
+        let doc = rtf_rs::Rtf::from_bytes(bytes).expect("must parse").parse();
+        let para = &doc.body.as_ref().expect("must have body").pages[0].sections[0].paras[0];
+        let zero = doc.run_format(para, &line(para, "zero"));
+        assert_eq!(zero.style.foreground_color, Some(0));
+        assert_eq!(zero.style.offset, Some(0));
+        let xml = docx_document_xml(&doc);
+        assert!(xml.contains(r#"<w:color w:val="auto" />"#));
+        assert!(xml.contains(r#"<w:position w:val="0" />"#));
+    }
+    #[test]
+    fn rtf_document_defaults() {
+        let bytes =
+            br"{\rtf1\deff0\stshfdbch2\stshfloch1\stshfhich1\stshfbi0\deflang1033\deflangfe1041
+{\fonttbl{\f0 Times;}{\f1 Arial;}{\f2 MS Mincho;}}{\*\defchp \b\fs22\loch\af1\dbch\af2 }
+{\*\defpap \qj\li100\sa120 }\pard\plain text\par}";
+        let doc = rtf_rs::Rtf::from_bytes(bytes).expect("must parse").parse();
+        let defaults = &doc.defaults;
+        assert_eq!(defaults.font, Some(0));
+        assert_eq!(defaults.style_fonts.east_asia, Some(2));
+        assert_eq!(defaults.style_fonts.complex, Some(0));
+        assert_eq!(defaults.associated_fonts.ascii, Some(1));
+        assert_eq!(defaults.fonts().high_ansi, Some(1));
+        assert_eq!(
+            defaults.font_style.as_ref().and_then(|style| style.size),
+            Some(22)
+        );
+        let para_style = defaults.para_style.as_ref().expect("must have defpap");
+        assert_eq!(para_style.align, Some(rtf_rs::Align::Justify));
+        assert_eq!(para_style.left_indent, Some(100));
+        assert_eq!(
+            (defaults.language, defaults.east_asian_language),
+            (Some(1033), Some(1041))
+        );
+        assert!(doc.destinations.is_empty());
+
+        let para = &doc.body.as_ref().expect("must have body").pages[0].sections[0].paras[0];
+        assert_eq!(doc.run_format(para, &para.lines[0]).style.size, Some(22));
+        assert_eq!(doc.paragraph_format(para).left_indent, Some(100));
+
+        let styles = String::from_utf8(doc.build_xml().styles).expect("must be utf-8");
+        assert!(styles.contains(
+            r#"<w:docDefaults><w:rPrDefault><w:rPr><w:rFonts w:ascii="Arial" w:hAnsi="Arial" w:cs="Times" w:eastAsia="MS Mincho" /><w:b /><w:bCs /><w:sz w:val="22" /><w:szCs w:val="22" /><w:lang w:val="en-US" w:eastAsia="ja-JP" /></w:rPr></w:rPrDefault><w:pPrDefault><w:pPr><w:spacing w:after="120" /><w:ind w:left="100" w:right="0" /><w:jc w:val="justified" /><w:rPr /></w:pPr></w:pPrDefault></w:docDefaults>"#
+        ));
+    }
+    #[test]
+    fn rtf_paragraph_spacing() {
+        let bytes = br"{\rtf1\pard\sb120\sa240\sl360\slmult1\contextualspace one\par
+\pard\sbauto1\sb100\saauto1\sa100\sl-280\slmult0 two\par\pard\lisb50\lisa100\sl240 three\par
+\pard four\par}";
+        let doc = rtf_rs::Rtf::from_bytes(bytes).expect("must parse").parse();
+        let paras = &doc.body.as_ref().expect("must have body").pages[0].sections[0].paras;
+        let one = paras[0].style.as_ref().expect("must have spacing");
+        assert_eq!((one.space_before, one.space_after), (Some(120), Some(240)));
+        assert_eq!(
+            (one.line_spacing, one.line_multiple),
+            (Some(360), Some(true))
+        );
+        assert_eq!(one.contextual_spacing, Some(true));
+        let two = paras[1].style.as_ref().expect("must have spacing");
+        assert_eq!(
+            (two.space_before_auto, two.space_after_auto),
+            (Some(true), Some(true))
+        );
+        assert_eq!(two.contextual_spacing, None);
+        let three = paras[2].style.as_ref().expect("must have spacing");
+        assert_eq!(
+            (three.space_before_lines, three.space_after_lines),
+            (Some(50), Some(100))
+        );
+        assert!(paras[3].style.is_none());
+
+        let document = docx_document_xml(&doc);
+        assert!(document.contains(
+            r#"<w:spacing w:before="120" w:after="240" w:line="360" w:lineRule="auto" /><w:contextualSpacing />"#
+        ));
+        assert_eq!(document.matches("<w:contextualSpacing").count(), 1);
+        assert!(document.contains(
+            r#"<w:spacing w:before="100" w:after="100" w:line="280" w:lineRule="exact" w:beforeAutospacing="1" w:afterAutospacing="1" />"#
+        ));
+        assert!(document.contains(
+            r#"<w:spacing w:beforeLines="50" w:afterLines="100" w:line="240" w:lineRule="atLeast" />"#
+        ));
+    }
+    #[test]
+    fn rtf_tab_stops() {
+        let bytes = br"{\rtf1\deftab708{\stylesheet{\tx1440 Normal;}}
+\pard\tqr\tldot\tx9000\tx1000\tqc\tleq\tx5000\tb3000 Item\tab 12.00\par\pard plain\par}";
+        let doc = rtf_rs::Rtf::from_bytes(bytes).expect("must parse").parse();
+        assert_eq!(doc.defaults.tab_width, Some(708));
+        let paras = &doc.body.as_ref().expect("must have body").pages[0].sections[0].paras;
+        let tabs = &paras[0].style.as_ref().expect("must have tabs").tabs;
+        let stop = |position, align, leader| rtf_rs::TabStop {
+            position,
+            align,
+            leader,
+        };
+        assert_eq!(
+            tabs,
+            &vec![
+                stop(9000, rtf_rs::TabAlign::Right, rtf_rs::TabLeader::Dot),
+                stop(1000, rtf_rs::TabAlign::Left, rtf_rs::TabLeader::None),
+                stop(5000, rtf_rs::TabAlign::Center, rtf_rs::TabLeader::Equal),
+                stop(3000, rtf_rs::TabAlign::Bar, rtf_rs::TabLeader::None),
+            ]
+        );
+        assert!(paras[1].style.is_none());
+        assert_eq!(doc.paragraph_format(&paras[1]).tabs[0].position, 1440);
+
+        let xml = doc.build_xml();
+        let document = String::from_utf8(xml.document).expect("must be utf-8");
+        assert!(document.contains(
+            r#"<w:tabs><w:tab w:val="right" w:leader="dot" w:pos="9000" /><w:tab w:val="left" w:pos="1000" /><w:tab w:val="center" w:leader="heavy" w:pos="5000" /><w:tab w:val="bar" w:pos="3000" /></w:tabs>"#
+        ));
+        assert!(document.contains(
+            r#"<w:t xml:space="preserve">Item</w:t><w:tab /><w:t xml:space="preserve">12.00</w:t>"#
+        ));
+        let settings = String::from_utf8(xml.settings).expect("must be utf-8");
+        assert!(settings.contains(r#"<w:defaultTabStop w:val="708" />"#));
+    }
+    #[test]
+    fn rtf_paragraph_borders() {
+        let bytes = br"{\rtf1{\colortbl;\red255\green0\blue0;\red0\green0\blue255;}
+\pard\box\brdrs\brdrw20\brdrcf1\brsp80\brdrb\brdrdb\brdrw40 Notice\par
+\pard\shading2500\cfpat1\cbpat2 Shaded\par\pard\bgdkcross\cbpat2 Hatched\par\pard plain\par}";
+        let doc = rtf_rs::Rtf::from_bytes(bytes).expect("must parse").parse();
+        let paras = &doc.body.as_ref().expect("must have body").pages[0].sections[0].paras;
+        let border = paras[0]
+            .style
+            .as_ref()
+            .and_then(|style| style.border.clone())
+            .expect("must have borders");
+        let top = border.top.expect("must have top");
+        assert_eq!(top.border_type, rtf_rs::BorderType::SingleThickness);
+        assert_eq!((top.width, top.color, top.space), (20, 1, 80));
+        let bottom = border.bottom.expect("must have bottom");
+        assert_eq!(bottom.border_type, rtf_rs::BorderType::Double);
+        assert_eq!((bottom.width, bottom.color, bottom.space), (40, 0, 0));
+        assert!(border.between.is_none());
+        let shading = paras[1]
+            .style
+            .as_ref()
+            .and_then(|style| style.shading.clone())
+            .expect("must have shading");
+        assert_eq!(shading.percent, 2500);
+        assert_eq!((shading.foreground_color, shading.background_color), (1, 2));
+        let pattern = paras[2]
+            .style
+            .as_ref()
+            .and_then(|style| style.shading.clone());
+        assert_eq!(
+            pattern.map(|shading| shading.pattern),
+            Some(rtf_rs::ShadingPattern::DarkCross)
+        );
+        assert!(paras[3].style.is_none());
+
+        let document = docx_document_xml(&doc);
+        assert!(document.contains(
+            r#"<w:pBdr><w:top w:val="single" w:sz="8" w:space="4" w:color="ff0000" /><w:left w:val="single" w:sz="8" w:space="4" w:color="ff0000" /><w:bottom w:val="double" w:sz="16" w:space="0" w:color="auto" /><w:right w:val="single" w:sz="8" w:space="4" w:color="ff0000" /></w:pBdr>"#
+        ));
+        assert!(document.contains(r#"<w:shd w:val="pct25" w:color="ff0000" w:fill="0000ff" />"#));
+        assert!(document.contains(r#"<w:shd w:val="horzCross" w:color="auto" w:fill="0000ff" />"#));
+    }
+    #[test]
+    fn rtf_border_styles() {
+        let bytes = br"{\rtf1{\colortbl;\red255\green0\blue0;}
+\pgbrdrt\brdrtriple\brdrw30\brsp480\brdrcf1\pgbrdrb\brdrart12
+\pard\brdrt\brdrtnthsg\brdrw20\brdrcf1\brsp40\brdrb\brdrwavy\brdrl\brdrsh\brdrw10 Para\par
+\trowd\trbrdrt\brdremboss\brdrw10\brdrcf1\brsp100\clbrdrb\brdrdashdot\brdrw20\brsp60
+\clbrdrl\brdrframe\brdrw10\cellx2000\intbl A\cell\row}";
+        let doc = rtf_rs::Rtf::from_bytes(bytes).expect("must parse").parse();
+        let section = &doc.body.as_ref().expect("must have body").pages[0].sections[0];
+        let page_border = section.page_border.clone().expect("must have page borders");
+        let top = page_border.top.expect("must have top");
+        assert_eq!(top.border_type, rtf_rs::BorderType::Triple);
+        assert_eq!((top.width, top.color, top.space), (30, 1, 480));
+        assert_eq!(
+            page_border.bottom.map(|border| border.border_type),
+            Some(rtf_rs::BorderType::Art(12))
+        );
+        let border = section.paras[0]
+            .style
+            .as_ref()
+            .and_then(|style| style.border.clone())
+            .expect("must have borders");
+        assert_eq!(
+            border.top.map(|border| border.border_type),
+            Some(rtf_rs::BorderType::ThinThickSmall)
+        );
+        assert_eq!(
+            border.bottom.map(|border| border.border_type),
+            Some(rtf_rs::BorderType::Wavy)
+        );
+
+        let document = docx_document_xml(&doc);
+        assert!(document.contains(
+            r#"<w:top w:val="thinThickSmallGap" w:sz="8" w:space="2" w:color="ff0000" />"#
+        ));
+        assert!(
+            document.contains(r#"<w:bottom w:val="wave" w:sz="0" w:space="0" w:color="auto" />"#)
+        );
+        assert!(document.contains(
+            r#"<w:left w:val="single" w:sz="4" w:space="0" w:color="auto" w:shadow="1" />"#
+        ));
+        assert!(document
+            .contains(r#"<w:top w:val="threeDEmboss" w:sz="4" w:space="5" w:color="ff0000" />"#));
+        assert!(document
+            .contains(r#"<w:bottom w:val="dotDash" w:sz="8" w:space="3" w:color="auto" />"#));
+        assert!(document.contains(
+            r#"<w:left w:val="single" w:sz="4" w:space="0" w:color="auto" w:frame="1" />"#
+        ));
+        assert!(!document.contains("extra"));
+        assert!(document.contains(
+            r#"<w:pgBorders><w:top w:val="triple" w:sz="12" w:space="24" w:color="ff0000" /><w:bottom w:val="basicWhiteDots" w:sz="0" w:space="0" w:color="auto" /></w:pgBorders><w:cols"#
+        ));
+    }
+    #[test]
+    fn rtf_section_page_borders() {
+        let bytes = br"{\rtf1{\colortbl;\red255\green0\blue0;}\pgbrdrt\brdrs\brdrw10 one\par
+\sect\pgbrdrb\brdrdb\brdrcf1 two\page three\par}";
+        let doc = rtf_rs::Rtf::from_bytes(bytes).expect("must parse").parse();
+        let document = docx_document_xml(&doc);
+        let first = r#"<w:pgBorders><w:top w:val="single" w:sz="4" w:space="0" w:color="auto" /></w:pgBorders>"#;
+        let last = r#"<w:pgBorders><w:bottom w:val="double" w:sz="0" w:space="0" w:color="ff0000" /></w:pgBorders>"#;
+        // the first section ends in a paragraph, the last one in the body
+        let first_section = document
+            .find("</w:sectPr></w:pPr>")
+            .expect("must end a section");
+        assert!(document[..first_section].contains(first));
+        let body_section = document.rfind("<w:sectPr>").expect("must have sectPr");
+        assert!(document[body_section..].contains(last));
+        assert_eq!(document.matches("<w:pgBorders>").count(), 2);
+    }
+    #[test]
+    fn rtf_table_grid() {
+        let bytes = br"{\rtf1\trowd\cellx3000\cellx6000\intbl A\cell B\cell\row
+\trowd\cellx2000\cellx6000\intbl C\cell D\cell\row}";
+        let doc = rtf_rs::Rtf::from_bytes(bytes).expect("must parse").parse();
+        let table = doc.body.as_ref().expect("must have body").pages[0].sections[0].paras[0]
+            .table
+            .clone()
+            .expect("must have table");
+        assert_eq!(table.cell_boundaries(), vec![0, 2000, 3000, 6000]);
+
+        let document = docx_document_xml(&doc);
+        assert!(document.contains(
+            r#"<w:tblGrid><w:gridCol w:w="2000" w:type="dxa" /><w:gridCol w:w="1000" w:type="dxa" /><w:gridCol w:w="3000" w:type="dxa" /></w:tblGrid>"#
+        ));
+        assert!(document.contains(r#"<w:tcW w:w="3000" w:type="dxa" /><w:gridSpan w:val="2" />"#));
+        assert!(document.contains(r#"<w:tcW w:w="2000" w:type="dxa" /><w:vAlign"#));
+        assert!(document.contains(r#"<w:tcW w:w="4000" w:type="dxa" /><w:gridSpan w:val="2" />"#));
+    }
+    #[test]
+    fn rtf_merged_cells() {
+        let bytes = br"{\rtf1\trowd\clmgf\cellx2000\clmrg\cellx4000\cellx6000\intbl Head\cell\cell X\cell\row
+\trowd\clvmgf\cellx2000\cellx4000\cellx6000\intbl A\cell B\cell C\cell\row
+\trowd\clvmrg\cellx2000\cellx4000\cellx6000\intbl \cell E\cell F\cell\row}";
+        let doc = rtf_rs::Rtf::from_bytes(bytes).expect("must parse").parse();
+        let document = docx_document_xml(&doc);
+        let rows: Vec<_> = document.split("<w:tr>").skip(1).collect();
+        assert_eq!(rows.len(), 3);
+        assert_eq!(rows[0].matches("<w:tc>").count(), 2);
+        assert!(rows[0].contains(
+            r#"<w:tcW w:w="4000" w:type="dxa" /><w:gridSpan w:val="2" /><w:vAlign w:val="top" /></w:tcPr><w:p"#
+        ));
+        assert!(rows[0].contains("Head"));
+        assert_eq!(rows[1].matches("<w:tc>").count(), 3);
+        assert!(rows[1].contains(r#"<w:vMerge w:val="restart" />"#));
+        assert!(rows[2].contains(r#"<w:vMerge w:val="continue" />"#));
+
+        // the merged cell's right border is that of the last cell in the merge
+        let bytes = br"{\rtf1\trowd\clmgf\clbrdrl\brdrs\clbrdrr\brdrs\cellx2000\clmrg\clbrdrr\brdrdb\cellx4000\intbl Head\cell\cell\row}";
+        let doc = rtf_rs::Rtf::from_bytes(bytes).expect("must parse").parse();
+        let document = docx_document_xml(&doc);
+        assert!(document.contains(
+            r#"<w:tcBorders><w:left w:val="single" w:sz="0" w:space="0" w:color="auto" /><w:right w:val="double" w:sz="0" w:space="0" w:color="auto" /></w:tcBorders>"#
+        ));
+    }
+    #[test]
+    fn rtf_fallback_encoding() {
+        // table cells keep the document's code page
+        let rtf = rtf_rs::Rtf::from_bytes(
+            br"{\rtf1\ansi\ansicpg1252\trowd\cellx1000\cellx2000\intbl caf\'e9\cell na\'efve\cell\row}",
+        )
+        .expect("must parse");
+        let doc = rtf.parse();
+        assert!(docx_document_xml(&doc).contains("café"));
+        assert!(docx_document_xml(&doc).contains("naïve"));
+
+        // no code page: \deflangfe, then the caller's default
+        let bytes = br"{\rtf1\deflang1033\deflangfe1041 \'82\'a0}";
+        let doc = rtf_rs::Rtf::from_bytes(bytes).expect("must parse").parse();
+        assert_eq!(doc.to_text(), "あ");
+        assert_eq!(doc.diagnostics.encoding_fallbacks().count(), 0);
+        let bytes = br"{\rtf1 \'e0\'e1}";
+        let rtf = rtf_rs::Rtf::from_bytes(bytes).expect("must parse");
+        assert_eq!(rtf.parse().to_text(), "àá");
+        let options = rtf_rs::ParseOptions {
+            default_encoding: Some(encoding_rs::WINDOWS_1251),
+            ..Default::default()
+        };
+        let doc = rtf.parse_with_options(&options).expect("must parse");
+        assert_eq!(doc.to_text(), "аб");
+        assert_eq!(doc.diagnostics.encoding_fallbacks().count(), 1);
+    }
+    /// xorshift, so the inputs are reproducible without extra dependencies
+    fn next_random(state: &mut u64) -> u64 {
+        *state ^= *state << 13;
+        *state ^= *state >> 7;
+        *state ^= *state << 17;
+        *state
+    }
+    fn convert_must_not_panic(bytes: &[u8]) {
+        if let Ok(rtf) = rtf_rs::Rtf::from_bytes(bytes) {
+            let doc = rtf.parse();
+            let _ = doc.to_text();
+            let _ = doc.to_docx();
+        }
+    }
+    #[test]
+    fn rtf_malformed_input_does_not_panic() {
+        const PIECES: &[&str] = &[
+            "{",
+            "}",
+            "{\\rtf1",
+            "\\ansi",
+            "\\ansicpg932",
+            "{\\fonttbl",
+            "{\\colortbl",
+            ";",
+            "{\\stylesheet",
+            "{\\*\\cs1",
+            "\\s1",
+            "\\f0",
+            "\\f",
+            "\\fcharset128",
+            "\\red255",
+            "\\green",
+            "\\blue-3",
+            "\\cf2",
+            "\\cb9",
+            "\\trowd",
+            "\\cellx100",
+            "\\cellx-5",
+            "\\clmgf",
+            "\\clmrg",
+            "\\clvmgf",
+            "\\clvmrg",
+            "\\clbrdrt",
+            "\\brdrs",
+            "\\brdrw-10",
+            "\\cell",
+            "\\row",
+            "\\lastrow",
+            "\\intbl",
+            "\\par",
+            "\\pard",
+            "\\sect",
+            "\\page",
+            "\\line",
+            "\\u12354",
+            "\\u-4064",
+            "\\uc0",
+            "\\uc5",
+            "\\'82",
+            "\\'a0",
+            "\\bin3 ",
+            "\\*",
+            "\\~",
+            "\\b",
+            "\\b0",
+            "\\i",
+            "\\ul0",
+            "\\fs24",
+            "\\fittext100",
+            "\\info",
+            "{\\author",
+            "{\\creatim",
+            "\\yr2020",
+            "\\tab",
+            "abc",
+            " ",
+            "\\\\",
+            "\\{",
+        ];
+        let mut state = 0x2545_f491_4f6c_dd1d;
+        for _ in 0..2000 {
+            let mut input = String::new();
+            let len = next_random(&mut state) % 40;
+            for _ in 0..len {
+                input.push_str(PIECES[(next_random(&mut state) as usize) % PIECES.len()]);
+            }
+            convert_must_not_panic(input.as_bytes());
+        }
+        const ALPHABET: &[u8] = b"{}\\'*-;0123456789abfinru \x82\xa0\r\n";
+        for _ in 0..3000 {
+            let len = next_random(&mut state) % 64;
+            let input: Vec<u8> = (0..len)
+                .map(|_| ALPHABET[(next_random(&mut state) as usize) % ALPHABET.len()])
+                .collect();
+            convert_must_not_panic(&input);
+        }
+        let sample = include_bytes!("./mocks/helloworld.rtf");
+        for _ in 0..20 {
+            let mut bytes = sample.to_vec();
+            for _ in 0..8 {
+                let at = (next_random(&mut state) as usize) % bytes.len();
+                bytes[at] = next_random(&mut state) as u8;
+            }
+            let cut = (next_random(&mut state) as usize) % bytes.len();
+            convert_must_not_panic(&bytes[..cut]);
+            convert_must_not_panic(&bytes);
+        }
+        let overflows: &[&[u8]] = &[
+            br"{\rtf1\bin18446744073709551610 x}",
+            br"{\rtf1\pard\shading-2147483647 x\par}",
+        ];
+        for bytes in overflows.iter() {
+            convert_must_not_panic(bytes);
+            if let Ok(doc) = rtf_rs::Rtf::from_reader(*bytes) {
+                let _ = doc.to_docx();
+            }
+        }
+    }
 }